@@ -0,0 +1,236 @@
+//! Options controlling the behaviour of this extension itself (as opposed to `texlab`),
+//! read from `lsp.texlab.initialization_options`.
+//!
+//! # Example
+//! ```json
+//! "lsp": {
+//!   "texlab": {
+//!     "initialization_options": {
+//!       "extra_tex_inputs": ["~/texmf/custom"],
+//!       "previewer": "sioyek",
+//!       "zed_command": "/opt/zed/bin/zed",
+//!       "autoconfig": { "on_save": false },
+//!       "texlab_version": "v5.21.0",
+//!       "build_profile": "draft",
+//!       "build_profiles": { "slides": ["-pdfxe"] },
+//!       "diagnostics_presets": ["quiet-boxes"],
+//!       "unicode_engine": "xelatex",
+//!       "build_backend": "tectonic",
+//!       "build_target": "pdf",
+//!       "shell_escape": "restricted"
+//!     }
+//!   }
+//! }
+//! ```
+use crate::project_config::Engine;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use zed_extension_api::{self as zed, serde_json};
+
+#[derive(Debug, Deserialize, JsonSchema, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExtensionOptions {
+    /// Directories prepended to the `TEXINPUTS` environment variable of `texlab`.
+    pub extra_tex_inputs: Vec<String>,
+    /// Previewer to use if available, instead of the first one detected
+    /// (`"none"` disables previewer detection).
+    pub previewer: Option<PreviewerChoice>,
+    /// Command used to launch Zed for inverse search, instead of the detected one.
+    pub zed_command: Option<String>,
+    pub autoconfig: Autoconfig,
+    pub texlab_version: TexlabVersion,
+    /// Active build profile (see `build_profiles`), unless chosen in the project configuration.
+    pub build_profile: Option<String>,
+    /// User-defined build profiles: extra `latexmk` arguments by profile name.
+    pub build_profiles: BTreeMap<String, Vec<String>>,
+    /// Named presets of diagnostics to ignore (`quiet-boxes`, `quiet-fonts`).
+    pub diagnostics_presets: Vec<String>,
+    /// Engine of the default build for documents loading packages such as `fontspec`
+    /// (unless chosen otherwise).
+    pub unicode_engine: UnicodeEngine,
+    /// Build tool of the default build command, unless chosen in the project configuration
+    /// (detected if not provided).
+    pub build_backend: Option<BuildBackend>,
+    /// `make` target or `just` recipe of the default build (`pdf` if not provided), unless
+    /// chosen in the project configuration.
+    pub build_target: Option<String>,
+    /// Whether the default build allows the document to run external tools (never enabled
+    /// unless chosen here).
+    pub shell_escape: Option<ShellEscape>,
+}
+
+/// Toggles for each of the settings this extension fills in when not provided by the user.
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct Autoconfig {
+    /// Default `latexmk` build command.
+    pub build: bool,
+    /// Switching on `build.onSave` and `build.forwardSearchAfter` when a previewer is detected.
+    pub on_save: bool,
+    /// Glyph preview of symbols on hover.
+    pub hover: bool,
+    /// Forward search settings for the detected previewer.
+    pub forward_search: bool,
+    /// Formatter settings derived from the editor settings and the availability of `latexindent`.
+    pub formatter: bool,
+    /// ChkTeX linting when `chktex` is available and the project has a `.chktexrc`.
+    pub chktex: bool,
+}
+
+impl Default for Autoconfig {
+    fn default() -> Self {
+        Autoconfig {
+            build: true,
+            on_save: true,
+            hover: true,
+            forward_search: true,
+            formatter: true,
+            chktex: true,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PreviewerChoice {
+    Zathura,
+    Skim,
+    Sioyek,
+    Qpdfview,
+    Okular,
+    Sumatrapdf,
+    Evince,
+    None,
+}
+
+#[derive(Debug, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BuildBackend {
+    Latexmk,
+    Tectonic,
+    Arara,
+    Make,
+    Just,
+}
+
+#[derive(Debug, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ShellEscape {
+    /// Any external tool may be run (`-shell-escape`)
+    Full,
+    /// Only the tools allowed by the TeX distribution (`-shell-restricted`)
+    Restricted,
+    /// No external tool may be run, even if the document needs it
+    Off,
+}
+
+#[derive(Debug, Deserialize, JsonSchema, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum UnicodeEngine {
+    #[default]
+    Lualatex,
+    Xelatex,
+}
+
+impl UnicodeEngine {
+    pub fn engine(&self) -> Engine {
+        match self {
+            UnicodeEngine::Lualatex => Engine::Lualatex,
+            UnicodeEngine::Xelatex => Engine::Xelatex,
+        }
+    }
+}
+
+/// Which release of `texlab` to download (if not provided by the user or found on PATH).
+#[derive(Debug, Deserialize, JsonSchema, Default, PartialEq, Eq)]
+#[serde(from = "String")]
+pub enum TexlabVersion {
+    /// `"latest"`: keep up to date with the latest GitHub release.
+    #[default]
+    Latest,
+    /// Any other string: the tag of a specific GitHub release, e.g. `"v5.21.0"`.
+    Pinned(String),
+}
+
+impl From<String> for TexlabVersion {
+    fn from(version: String) -> Self {
+        if version == "latest" {
+            TexlabVersion::Latest
+        } else {
+            TexlabVersion::Pinned(version)
+        }
+    }
+}
+
+impl ExtensionOptions {
+    /// Reads the options from `lsp.texlab.initialization_options` for the worktree.
+    pub fn for_worktree(worktree: &zed::Worktree) -> Result<Self, String> {
+        Self::from_value(
+            zed::settings::LspSettings::for_worktree("texlab", worktree)
+                .ok()
+                .and_then(|lsp_settings| lsp_settings.initialization_options)
+                .unwrap_or_default(),
+        )
+    }
+
+    fn from_value(value: serde_json::Value) -> Result<Self, String> {
+        serde_json::from_value::<Option<Self>>(value)
+            .map(Option::unwrap_or_default)
+            .map_err(|err| format!("invalid lsp.texlab.initialization_options: {err}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zed_extension_api::serde_json::json;
+
+    #[test]
+    fn test_missing_options_use_defaults() {
+        let options = ExtensionOptions::from_value(serde_json::Value::Null).unwrap();
+        assert!(options.extra_tex_inputs.is_empty());
+        assert!(options.previewer.is_none());
+        assert!(options.autoconfig.build && options.autoconfig.forward_search);
+        assert_eq!(options.texlab_version, TexlabVersion::Latest);
+        assert_eq!(options.unicode_engine, UnicodeEngine::Lualatex);
+    }
+
+    #[test]
+    fn test_deserialize_options() {
+        let options = ExtensionOptions::from_value(json!({
+            "extra_tex_inputs": ["styles"],
+            "previewer": "sioyek",
+            "zed_command": "zed-preview",
+            "autoconfig": { "on_save": false },
+            "texlab_version": "v5.21.0",
+            "build_profile": "slides",
+            "build_profiles": { "slides": ["-pdfxe"] },
+            "diagnostics_presets": ["quiet-fonts"],
+            "unicode_engine": "xelatex",
+            "build_backend": "make",
+            "build_target": "thesis"
+        }))
+        .unwrap();
+        assert_eq!(options.extra_tex_inputs, vec!["styles".to_string()]);
+        assert_eq!(options.previewer, Some(PreviewerChoice::Sioyek));
+        assert_eq!(options.zed_command.as_deref(), Some("zed-preview"));
+        assert!(!options.autoconfig.on_save && options.autoconfig.hover);
+        assert_eq!(
+            options.texlab_version,
+            TexlabVersion::Pinned("v5.21.0".to_string())
+        );
+        assert_eq!(options.build_profile.as_deref(), Some("slides"));
+        assert_eq!(options.build_profiles["slides"], ["-pdfxe"]);
+        assert_eq!(options.diagnostics_presets, ["quiet-fonts"]);
+        assert_eq!(options.unicode_engine, UnicodeEngine::Xelatex);
+        assert_eq!(options.build_backend, Some(BuildBackend::Make));
+        assert_eq!(options.build_target.as_deref(), Some("thesis"));
+    }
+
+    #[test]
+    fn test_unknown_options_are_rejected() {
+        let err = ExtensionOptions::from_value(json!({ "previwer": "zathura" })).unwrap_err();
+        assert!(err.contains("unknown field `previwer`"));
+    }
+}
@@ -1,8 +1,16 @@
+mod extension_options;
+mod project_config;
 mod texlab_invocation;
 mod texlab_workspace_config;
 mod zed_command;
 
+use extension_options::ExtensionOptions;
+use project_config::ProjectConfig;
+use std::collections::HashSet;
+use texlab_workspace_config::formatting::FormattingPreferences;
 use texlab_workspace_config::preview_presets::Preview;
+use texlab_workspace_config::root_document::RootDocument;
+use texlab_workspace_config::tool_detection::DetectedTools;
 use zed_command::CommandName;
 use zed_extension_api::{self as zed, serde_json};
 
@@ -15,6 +23,33 @@ struct LatexExtension {
     previewer: Option<Preview>,
     /// Executable to invoke the zed editor (None if not on PATH)
     zed_command: Option<CommandName>,
+    /// Options read from `lsp.texlab.initialization_options`
+    options: ExtensionOptions,
+    /// Project-local configuration read from `.zed-latex.toml`
+    project: ProjectConfig,
+    /// Notes about the configuration already written to the extension's standard error, so
+    /// that each is written once rather than on every configuration request
+    reported_notes: HashSet<String>,
+}
+
+impl LatexExtension {
+    /// Writes `note` to the extension's standard error, unless it was already written. The
+    /// notes are also listed by `/texlab-config`, which is where users see them.
+    fn report(&mut self, note: String) {
+        if !self.reported_notes.contains(&note) {
+            eprintln!("{note}");
+            self.reported_notes.insert(note);
+        }
+    }
+
+    /// The configuration read, or else the defaults, so that a mistake in it doesn't keep
+    /// texlab from starting (the error is listed by `/texlab-config`).
+    fn or_default<T: Default>(&mut self, config: Result<T, String>) -> T {
+        config.unwrap_or_else(|err| {
+            self.report(format!("{err}, the defaults were used instead"));
+            T::default()
+        })
+    }
 }
 
 impl zed::Extension for LatexExtension {
@@ -38,8 +73,11 @@ impl zed::Extension for LatexExtension {
         // (this has nothing to do with the language server but this
         // is a convenient place to minimize the number of times this
         // is done).
-        self.previewer = Preview::determine(worktree);
-        self.zed_command = CommandName::determine(worktree);
+        self.options = self.or_default(ExtensionOptions::for_worktree(worktree));
+        self.project = self.or_default(ProjectConfig::for_worktree(worktree));
+        self.previewer =
+            Preview::determine(worktree, self.project.previewer.or(self.options.previewer));
+        self.zed_command = CommandName::determine(worktree, self.options.zed_command.as_deref());
 
         texlab_invocation::command(self, language_server_id, worktree)
     }
@@ -49,20 +87,90 @@ impl zed::Extension for LatexExtension {
         _language_server_id: &zed::LanguageServerId,
         worktree: &zed::Worktree,
     ) -> zed::Result<Option<zed::serde_json::Value>> {
-        let settings = zed::settings::LspSettings::for_worktree("texlab", worktree)
-            .ok()
-            .and_then(|lsp_settings| lsp_settings.settings.clone())
-            .unwrap_or_default();
+        // Re-read in case the options were changed since the language server was started.
+        self.options = self.or_default(ExtensionOptions::for_worktree(worktree));
+        self.project = self.or_default(ProjectConfig::for_worktree(worktree));
 
-        Ok(Some(
-            serde_json::to_value(texlab_workspace_config::get(
-                &self.previewer,
-                self.zed_command.unwrap_or_default(),
-                settings,
-            )?)
-            .unwrap_or_default(),
-        ))
+        let tools = DetectedTools::for_worktree(worktree);
+        let (settings, notes) = texlab_workspace_config::get(
+            &texlab_workspace_config::Context {
+                previewer: &self.previewer,
+                zed_command: &self.zed_command.clone().unwrap_or_default(),
+                options: &self.options,
+                formatting: &FormattingPreferences::for_worktree(worktree),
+                project: &self.project,
+                document: &RootDocument::for_worktree(
+                    worktree,
+                    &self.project,
+                    tools.latexmkrc.as_ref(),
+                ),
+                tools: &tools,
+            },
+            texlab_settings(worktree),
+        )?;
+        for note in notes {
+            self.report(format!("texlab settings: {note}"));
+        }
+        Ok(Some(serde_json::to_value(settings).unwrap_or_default()))
     }
+
+    fn run_slash_command(
+        &self,
+        command: zed::SlashCommand,
+        _args: Vec<String>,
+        worktree: Option<&zed::Worktree>,
+    ) -> zed::Result<zed::SlashCommandOutput> {
+        match command.name.as_str() {
+            "texlab-config" => {
+                let worktree = worktree.ok_or("no worktree to read the texlab settings from")?;
+                let project = ProjectConfig::for_worktree(worktree);
+                let options = ExtensionOptions::for_worktree(worktree);
+                let errors = [project.as_ref().err(), options.as_ref().err()]
+                    .into_iter()
+                    .flatten()
+                    .cloned()
+                    .collect::<Vec<_>>();
+                let project = project.unwrap_or_default();
+                let options = options.unwrap_or_default();
+                let tools = DetectedTools::for_worktree(worktree);
+                let mut text = texlab_workspace_config::describe(
+                    &texlab_workspace_config::Context {
+                        previewer: &self.previewer,
+                        zed_command: &self.zed_command.clone().unwrap_or_default(),
+                        options: &options,
+                        formatting: &FormattingPreferences::for_worktree(worktree),
+                        project: &project,
+                        document: &RootDocument::for_worktree(
+                            worktree,
+                            &project,
+                            tools.latexmkrc.as_ref(),
+                        ),
+                        tools: &tools,
+                    },
+                    texlab_settings(worktree),
+                )?;
+                for err in errors {
+                    text.push_str(&format!("\n\nNote: {err}, the defaults were used instead"));
+                }
+                Ok(zed::SlashCommandOutput {
+                    sections: vec![zed::SlashCommandOutputSection {
+                        range: (0..text.len()).into(),
+                        label: "Effective texlab configuration".to_string(),
+                    }],
+                    text,
+                })
+            }
+            command => Err(format!("unknown slash command: \"{command}\"")),
+        }
+    }
+}
+
+/// The `lsp.texlab.settings` provided by the user for the worktree (`null` if not provided).
+fn texlab_settings(worktree: &zed::Worktree) -> serde_json::Value {
+    zed::settings::LspSettings::for_worktree("texlab", worktree)
+        .ok()
+        .and_then(|lsp_settings| lsp_settings.settings.clone())
+        .unwrap_or_default()
 }
 
 zed::register_extension!(LatexExtension);
@@ -0,0 +1,184 @@
+//! Project-local LaTeX configuration, read from `.zed-latex.toml` at the root of the worktree.
+//!
+//! Unlike `.zed/settings.json`, this file only concerns this extension, and describes how
+//! the LaTeX documents of the project are built and previewed.
+//!
+//! # Example
+//! ```toml
+//! root = "thesis/main.tex"
+//! engine = "lualatex"
+//! output_directory = "build"
+//! previewer = "zathura"
+//! tex_inputs = ["styles"]
+//! profile = "draft"
+//! backend = "latexmk"
+//! ```
+//!
+//! Projects built with `make` or `just` name the target and the PDF it produces:
+//! ```toml
+//! backend = "make"
+//! target = "pdf"
+//! pdf = "build/thesis.pdf"
+//! ```
+use crate::extension_options::{BuildBackend, PreviewerChoice};
+use serde::Deserialize;
+use zed_extension_api as zed;
+
+pub const PROJECT_CONFIG_FILE: &str = ".zed-latex.toml";
+
+#[derive(Debug, Deserialize, Default, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProjectConfig {
+    /// Root document to build, instead of the file being edited (relative to the worktree root).
+    pub root: Option<String>,
+    /// TeX engine used by the default `latexmk` build.
+    pub engine: Option<Engine>,
+    /// Directory for the build artifacts (relative to the root document).
+    pub output_directory: Option<String>,
+    /// Previewer to use if available, taking precedence over the `previewer` extension option.
+    pub previewer: Option<PreviewerChoice>,
+    /// Directories prepended to `TEXINPUTS` (relative to the worktree root).
+    pub tex_inputs: Vec<String>,
+    /// Active build profile, taking precedence over the `build_profile` extension option.
+    pub profile: Option<String>,
+    /// Build tool, taking precedence over the `build_backend` extension option.
+    pub backend: Option<BuildBackend>,
+    /// `make` target or `just` recipe, taking precedence over the `build_target` extension option.
+    pub target: Option<String>,
+    /// PDF produced by the build (relative to the worktree root), if not named after the
+    /// root document in the output directory.
+    pub pdf: Option<String>,
+    /// No longer read: shell escape is only enabled by the `shell_escape` extension option.
+    #[serde(rename = "shell_escape")]
+    pub ignored_shell_escape: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Engine {
+    Pdflatex,
+    Xelatex,
+    Lualatex,
+    /// Plain `latex`, producing DVI output
+    Latex,
+}
+
+impl Engine {
+    /// Engine of a TeX program name as used in `% !TEX program` magic comments (case
+    /// insensitive, also accepting TeXShop's `latexmk` variants, e.g. `xelatexmk`, where plain
+    /// `latexmk` builds a PDF with pdfLaTeX).
+    pub fn from_program(program: &str) -> Option<Self> {
+        let program = program.to_ascii_lowercase();
+        if program == "latexmk" {
+            return Some(Engine::Pdflatex);
+        }
+        match program.strip_suffix("mk").unwrap_or(&program) {
+            "pdflatex" => Some(Engine::Pdflatex),
+            "xelatex" => Some(Engine::Xelatex),
+            "lualatex" => Some(Engine::Lualatex),
+            "latex" => Some(Engine::Latex),
+            _ => None,
+        }
+    }
+
+    /// `latexmk` flag selecting the engine.
+    pub fn latexmk_flag(&self) -> &'static str {
+        match self {
+            Engine::Pdflatex => "-pdf",
+            Engine::Xelatex => "-pdfxe",
+            Engine::Lualatex => "-pdflua",
+            Engine::Latex => "-dvi",
+        }
+    }
+
+    /// `latexmk`'s `$pdf_mode` selecting the engine (`0` for DVI output).
+    pub fn pdf_mode(&self) -> u8 {
+        match self {
+            Engine::Pdflatex => 1,
+            Engine::Xelatex => 5,
+            Engine::Lualatex => 4,
+            Engine::Latex => 0,
+        }
+    }
+}
+
+impl ProjectConfig {
+    /// Reads `.zed-latex.toml` from the worktree, resolving paths relative to the worktree root.
+    /// A missing file is equivalent to an empty one.
+    pub fn for_worktree(worktree: &zed::Worktree) -> Result<Self, String> {
+        match worktree.read_text_file(PROJECT_CONFIG_FILE) {
+            Ok(contents) => Self::parse(&contents, &worktree.root_path()),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    fn parse(contents: &str, worktree_root: &str) -> Result<Self, String> {
+        let mut config: Self = toml::from_str(contents)
+            .map_err(|err| format!("invalid {PROJECT_CONFIG_FILE}: {err}"))?;
+        let resolve = |path: &String| format!("{worktree_root}/{path}");
+        config.root = config.root.as_ref().map(resolve);
+        config.pdf = config.pdf.as_ref().map(resolve);
+        config.tex_inputs = config.tex_inputs.iter().map(resolve).collect();
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_project_config() {
+        let config = ProjectConfig::parse(
+            r#"
+root = "thesis/main.tex"
+engine = "lualatex"
+output_directory = "build"
+previewer = "zathura"
+tex_inputs = ["styles"]
+profile = "final"
+backend = "tectonic"
+target = "thesis"
+pdf = "out/thesis.pdf"
+shell_escape = "restricted"
+"#,
+            "/home/user/project",
+        )
+        .unwrap();
+        assert_eq!(
+            config,
+            ProjectConfig {
+                root: Some("/home/user/project/thesis/main.tex".to_string()),
+                engine: Some(Engine::Lualatex),
+                output_directory: Some("build".to_string()),
+                previewer: Some(PreviewerChoice::Zathura),
+                tex_inputs: vec!["/home/user/project/styles".to_string()],
+                profile: Some("final".to_string()),
+                backend: Some(BuildBackend::Tectonic),
+                target: Some("thesis".to_string()),
+                pdf: Some("/home/user/project/out/thesis.pdf".to_string()),
+                ignored_shell_escape: Some("restricted".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn test_engine_from_program() {
+        assert_eq!(Engine::from_program("LuaLaTeX"), Some(Engine::Lualatex));
+        assert_eq!(Engine::from_program("xelatexmk"), Some(Engine::Xelatex));
+        assert_eq!(Engine::from_program("latexmk"), Some(Engine::Pdflatex));
+        assert_eq!(Engine::from_program("latex"), Some(Engine::Latex));
+        assert_eq!(Engine::from_program("context"), None);
+    }
+
+    #[test]
+    fn test_parse_errors_name_the_file() {
+        let err = ProjectConfig::parse("engin = \"xelatex\"", "/project").unwrap_err();
+        assert!(err.starts_with("invalid .zed-latex.toml"));
+        assert!(err.contains("unknown field `engin`"));
+        assert_eq!(
+            ProjectConfig::parse("", "/project"),
+            Ok(ProjectConfig::default())
+        );
+    }
+}
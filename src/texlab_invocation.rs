@@ -4,6 +4,7 @@
 //!
 //! [`texlab`]: https://github.com/latex-lsp/texlab
 use super::LatexExtension;
+use crate::extension_options::TexlabVersion;
 use zed_extension_api as zed;
 
 /// Constructs the command to start the `texlab` language server.
@@ -12,13 +13,15 @@ use zed_extension_api as zed;
 /// 1. Use a user-provided path from settings
 /// 2. Use a binary available on PATH
 /// 3. Use a previously downloaded binary (from number 4 in a previous run)
-/// 4. Download the latest release from GitHub
-///   (using previously downloaded release if still current, or as a fallback to any network errors)
+/// 4. Download the latest release from GitHub, or the release pinned by the
+///    `texlab_version` extension option
+///    (using previously downloaded release if still current, or as a fallback to any network errors)
 ///
 /// In all cases apart from the user-provided case, provide no CLI arguments to `texlab`.
 ///
 /// This also adjusts the `TEXINPUTS` environment variable if
-/// "lsp.texlab.initialization_options.extra_tex_inputs" zed setting is provided.
+/// "lsp.texlab.initialization_options.extra_tex_inputs" zed setting, or `tex_inputs` in the
+/// project configuration file, is provided.
 pub fn command(
     latex_extension: &mut LatexExtension,
     language_server_id: &zed_extension_api::LanguageServerId,
@@ -37,7 +40,14 @@ pub fn command(
         _ => vec![],
     };
 
-    let env = Default::default();
+    let tex_inputs: Vec<String> = latex_extension
+        .project
+        .tex_inputs
+        .iter()
+        .chain(&latex_extension.options.extra_tex_inputs)
+        .cloned()
+        .collect();
+    let env = tex_inputs_env(&tex_inputs, worktree);
 
     // First priority for texlab executable: user-provided path.
     if let Some(CommandSettings {
@@ -54,16 +64,23 @@ pub fn command(
         return Ok(zed::Command { command, args, env });
     }
 
-    // Third priority for texlab: cached path (from download in final priority).
+    let version = &latex_extension.options.texlab_version;
+
+    // Third priority for texlab: cached path (from download in final priority),
+    // unless it is not the pinned version.
     if let Some(ref path) = latex_extension.cached_texlab_path {
-        if std::fs::metadata(path).is_ok() {
+        let is_pinned_version = match version {
+            TexlabVersion::Latest => true,
+            TexlabVersion::Pinned(tag) => path.starts_with(&format!("texlab-{tag}/")),
+        };
+        if is_pinned_version && std::fs::metadata(path).is_ok() {
             let command = path.clone();
             return Ok(zed::Command { command, args, env });
         }
     }
 
     // Final priority for texlab: download from GitHub releases.
-    let binary_path = acquire_latest_texlab(language_server_id)?;
+    let binary_path = acquire_texlab(language_server_id, version)?;
     latex_extension.cached_texlab_path = Some(binary_path.clone());
 
     Ok(zed::Command {
@@ -73,37 +90,64 @@ pub fn command(
     })
 }
 
-// Download the latest release of `texlab` from GitHub and return the path to the binary,
-// updating the language server installation status along the way.
+/// Build the environment for `texlab`, prepending `extra_tex_inputs` (if any) to `TEXINPUTS`.
+/// The trailing separator keeps TeX's default search path.
+fn tex_inputs_env(extra_tex_inputs: &[String], worktree: &zed::Worktree) -> Vec<(String, String)> {
+    if extra_tex_inputs.is_empty() {
+        return vec![];
+    }
+    let separator = match zed::current_platform().0 {
+        zed::Os::Windows => ";",
+        zed::Os::Mac | zed::Os::Linux => ":",
+    };
+    let existing = worktree
+        .shell_env()
+        .into_iter()
+        .find(|(name, _)| name == "TEXINPUTS")
+        .map(|(_, value)| value)
+        .unwrap_or_default();
+    vec![(
+        "TEXINPUTS".to_string(),
+        format!("{}{separator}{existing}", extra_tex_inputs.join(separator)),
+    )]
+}
+
+// Download the latest (or pinned) release of `texlab` from GitHub and return the path to the
+// binary, updating the language server installation status along the way.
 // Cache the location if downloaded to be used the next time if available.
 // If previously downloaded, skip download.
 // If no network, search if previously downloaded.
-fn acquire_latest_texlab(
+fn acquire_texlab(
     language_server_id: &zed_extension_api::LanguageServerId,
+    version: &TexlabVersion,
 ) -> Result<String, String> {
     let (platform, arch) = zed::current_platform();
     zed::set_language_server_installation_status(
         language_server_id,
         &zed::LanguageServerInstallationStatus::CheckingForUpdate,
     );
-    let release = match zed::latest_github_release(
-        "latex-lsp/texlab",
-        zed::GithubReleaseOptions {
-            require_assets: true,
-            pre_release: false,
-        },
-    ) {
+    let release = match version {
+        TexlabVersion::Latest => zed::latest_github_release(
+            "latex-lsp/texlab",
+            zed::GithubReleaseOptions {
+                require_assets: true,
+                pre_release: false,
+            },
+        ),
+        TexlabVersion::Pinned(tag) => zed::github_release_by_tag_name("latex-lsp/texlab", tag),
+    };
+    let release = match release {
         Ok(release) => release,
         Err(e) => {
             zed::set_language_server_installation_status(
                 language_server_id,
                 &zed::LanguageServerInstallationStatus::Failed(format!(
-                    "Error finding latest GitHub release for texlab: {e}"
+                    "Error finding GitHub release for texlab: {e}"
                 )),
             );
             // Fallback: check if we can find any previously downloaded releases.
             // Do not cache in case network connection recovered later.
-            return find_previously_downloaded_texlab_release(platform);
+            return find_previously_downloaded_texlab_release(platform, version);
         }
     };
     let arch: &str = match arch {
@@ -130,7 +174,7 @@ fn acquire_latest_texlab(
         zed::Os::Mac | zed::Os::Linux => format!("{version_dir}/texlab"),
         zed::Os::Windows => format!("{version_dir}/texlab.exe"),
     };
-    if !std::fs::metadata(&binary_path).map_or(false, |stat| stat.is_file()) {
+    if !std::fs::metadata(&binary_path).is_ok_and(|stat| stat.is_file()) {
         zed::set_language_server_installation_status(
             language_server_id,
             &zed::LanguageServerInstallationStatus::Downloading,
@@ -163,20 +207,27 @@ fn acquire_latest_texlab(
 
 /// Check if there are any previously downloaded GitHub releases.
 /// These will be downloaded as `texlab(.exe)` in a directory `texlab-VERSION`.
-/// Return the latest (largest version number) if any is found.
-fn find_previously_downloaded_texlab_release(platform: zed::Os) -> Result<String, String> {
+/// Return the latest (largest version number) if any is found, or the pinned version.
+fn find_previously_downloaded_texlab_release(
+    platform: zed::Os,
+    version: &TexlabVersion,
+) -> Result<String, String> {
     let entries =
         std::fs::read_dir(".").map_err(|e| format!("failed to list working directory {e}"))?;
     let downloaded_releases = entries.filter_map(|dir| {
         let dir_name: String = dir.ok()?.file_name().to_str()?.to_owned();
-        if !dir_name.starts_with("texlab-") {
+        let is_wanted_version = match version {
+            TexlabVersion::Latest => dir_name.starts_with("texlab-"),
+            TexlabVersion::Pinned(tag) => dir_name == format!("texlab-{tag}"),
+        };
+        if !is_wanted_version {
             return None;
         }
         let binary_path = match platform {
             zed::Os::Mac | zed::Os::Linux => format!("{}/texlab", dir_name),
             zed::Os::Windows => format!("{}/texlab.exe", dir_name),
         };
-        if std::fs::metadata(&binary_path).map_or(false, |stat| stat.is_file()) {
+        if std::fs::metadata(&binary_path).is_ok_and(|stat| stat.is_file()) {
             return Some(binary_path);
         }
         None
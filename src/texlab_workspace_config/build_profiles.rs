@@ -0,0 +1,102 @@
+//! Named build profiles, deciding the extra `latexmk` arguments of the default build command.
+//!
+//! Two profiles are built in:
+//! - `draft`: a single pass without bibliography, with the `draft` class option
+//!   (e.g. images are not loaded), to keep long documents fast to iterate on. The option is
+//!   passed to the class of the root document if detected, or else to common classes.
+//! - `final`: a full run (forced even if up to date) processing the bibliography with
+//!   bibtex/biber as needed, so the output is correct.
+//!
+//! Users can define their own (or override the built-in ones) with the `build_profiles`
+//! extension option, and choose the active one with the `build_profile` extension option
+//! or `profile` in the project configuration file.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Document classes to which the `draft` option is passed in the `draft` profile when the
+/// class of the root document is not known.
+/// (`\PassOptionsToClass` needs the class name, and only applies to the class being loaded.)
+const DRAFT_CLASSES: &[&str] = &[
+    "article", "report", "book", "memoir", "amsart", "amsbook", "scrartcl", "scrreprt", "scrbook",
+];
+
+/// Extra `latexmk` arguments for the profile named `name`, for a root document of the
+/// given `class` (if known).
+pub fn profile_args(
+    name: &str,
+    custom_profiles: &BTreeMap<String, Vec<String>>,
+    class: Option<&str>,
+) -> Result<Vec<String>, String> {
+    if let Some(args) = custom_profiles.get(name) {
+        return Ok(args.clone());
+    }
+    match name {
+        "draft" => Ok(vec![
+            "-e".into(),
+            "$max_repeat = 1;".into(),
+            "-bibtex-".into(),
+            format!(
+                "-usepretex={}",
+                class
+                    .map_or_else(|| DRAFT_CLASSES.to_vec(), |class| vec![class])
+                    .iter()
+                    .map(|class| format!("\\PassOptionsToClass{{draft}}{{{class}}}"))
+                    .collect::<String>()
+            ),
+        ]),
+        "final" => Ok(vec!["-bibtex".into(), "-g".into()]),
+        _ => Err(format!(
+            "unknown build profile \"{name}\", expected one of {}",
+            ["draft", "final"]
+                .into_iter()
+                .chain(custom_profiles.keys().map(String::as_str))
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .map(|name| format!("\"{name}\""))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_profiles() {
+        let draft = profile_args("draft", &BTreeMap::new(), None).unwrap();
+        assert!(draft.contains(&"-bibtex-".to_string()));
+        assert!(draft
+            .last()
+            .unwrap()
+            .starts_with("-usepretex=\\PassOptionsToClass{draft}{article}"));
+        assert_eq!(
+            profile_args("final", &BTreeMap::new(), None).unwrap(),
+            ["-bibtex", "-g"]
+        );
+    }
+
+    #[test]
+    fn test_draft_option_is_passed_to_detected_class() {
+        let draft = profile_args("draft", &BTreeMap::new(), Some("beamer")).unwrap();
+        assert_eq!(
+            draft.last().unwrap(),
+            "-usepretex=\\PassOptionsToClass{draft}{beamer}"
+        );
+    }
+
+    #[test]
+    fn test_custom_profiles_override_builtin_ones() {
+        let custom = BTreeMap::from([
+            ("final".to_string(), vec!["-gg".to_string()]),
+            ("slides".to_string(), vec!["-pdfxe".to_string()]),
+        ]);
+        assert_eq!(profile_args("final", &custom, None).unwrap(), ["-gg"]);
+        assert_eq!(profile_args("slides", &custom, None).unwrap(), ["-pdfxe"]);
+        assert_eq!(
+            profile_args("fast", &custom, None).unwrap_err(),
+            "unknown build profile \"fast\", expected one of \"draft\", \"final\", \"slides\""
+        );
+    }
+}
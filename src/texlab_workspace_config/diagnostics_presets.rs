@@ -0,0 +1,85 @@
+//! Named presets of `diagnostics.ignoredPatterns`, silencing the build warnings that are
+//! usually noise when working on real documents:
+//! - `quiet-boxes`: overfull and underfull `\hbox`/`\vbox` warnings.
+//! - `quiet-fonts`: font shape and size substitution warnings.
+//!
+//! Presets are chosen with the `diagnostics_presets` extension option, and are added to
+//! the patterns provided by the user (if any).
+
+const PRESETS: &[(&str, &[&str])] = &[
+    ("quiet-boxes", &[r"(Overfull|Underfull) \\[hv]box"]),
+    (
+        "quiet-fonts",
+        &[
+            r"Font shape .* (undefined|in size)",
+            r"Some font shapes were not available",
+            r"Size substitutions with differences",
+        ],
+    ),
+];
+
+/// Adds the `ignoredPatterns` regexes of the presets named `names` to `user_patterns`
+/// (skipping the ones already present).
+///
+/// Returns the patterns along with a description of each unknown preset, which is skipped.
+pub fn ignored_patterns(
+    names: &[String],
+    user_patterns: Vec<String>,
+) -> (Vec<String>, Vec<String>) {
+    let mut patterns = user_patterns;
+    let mut problems = Vec::new();
+    for name in names {
+        let Some((_, preset_patterns)) = PRESETS.iter().find(|(preset, _)| preset == name) else {
+            problems.push(format!(
+                "unknown diagnostics preset \"{name}\", expected one of {}",
+                PRESETS
+                    .iter()
+                    .map(|(preset, _)| format!("\"{preset}\""))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+            continue;
+        };
+        for pattern in *preset_patterns {
+            if !patterns.iter().any(|existing| existing == pattern) {
+                patterns.push(pattern.to_string());
+            }
+        }
+    }
+    (patterns, problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presets_are_merged_with_user_patterns() {
+        let (patterns, problems) = ignored_patterns(
+            &["quiet-boxes".to_string(), "quiet-boxes".to_string()],
+            vec!["Citation .* undefined".to_string()],
+        );
+        assert!(problems.is_empty());
+        assert_eq!(
+            patterns,
+            ["Citation .* undefined", r"(Overfull|Underfull) \\[hv]box"]
+        );
+        assert_eq!(
+            ignored_patterns(&["quiet-fonts".to_string()], vec![])
+                .0
+                .len(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_unknown_preset_is_skipped() {
+        let (patterns, problems) =
+            ignored_patterns(&["quiet".to_string(), "quiet-boxes".to_string()], vec![]);
+        assert_eq!(patterns, [r"(Overfull|Underfull) \\[hv]box"]);
+        assert_eq!(
+            problems,
+            ["unknown diagnostics preset \"quiet\", expected one of \"quiet-boxes\", \"quiet-fonts\""]
+        );
+    }
+}
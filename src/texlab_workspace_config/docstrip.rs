@@ -0,0 +1,35 @@
+//! Builds of docstrip sources, as written by package authors: saving a `.ins` file runs
+//! `tex` on it to generate the package files, while `.dtx` files are built like ordinary
+//! documents, but with the index and change history styles of the `doc` package.
+//!
+//! The default `latexmk` build includes this code when the document it builds can be a
+//! docstrip source (see `may_build`), after the root document has been followed.
+
+/// Perl code for `latexmk -e` handling `.ins` and `.dtx` documents.
+///
+/// `.ins` files are run through `tex` from their directory (where docstrip expects the `.dtx`
+/// files), and `latexmk` exits with its status. SyncTeX data of `.dtx` builds refers to the
+/// `.dtx` file itself, so forward search works as for any other document.
+pub const LATEXMK_SNIPPET: &str = concat!(
+    "if ($ARGV[-1] =~ /\\.ins$/) { ",
+    "my ($dir, $file) = $ARGV[-1] =~ m{^(.*[/\\\\])?([^/\\\\]*)$}; ",
+    "chdir $dir if defined $dir; ",
+    "exit(system('tex', '-interaction=nonstopmode', $file) == 0 ? 0 : 1); ",
+    "} ",
+    "if ($ARGV[-1] =~ /\\.dtx$/) { ",
+    "$makeindex = 'makeindex -s gind.ist %O -o %D %S'; ",
+    "add_cus_dep('glo', 'gls', 0, 'makeglo'); ",
+    "} ",
+    "sub makeglo { return system('makeindex', '-s', 'gglo.ist', '-o', \"$_[0].gls\", \"$_[0].glo\"); }",
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snippet_sorts_index_and_change_history() {
+        assert!(LATEXMK_SNIPPET.contains("gind.ist"));
+        assert!(LATEXMK_SNIPPET.contains("gglo.ist"));
+    }
+}
@@ -0,0 +1,180 @@
+//! Formatting preferences of the worktree, used as defaults for texlab's formatter settings
+//! (`formatterLineLength` and `latexindent`) so that texlab formats documents the same way
+//! Zed edits them.
+//!
+//! The Zed extension API only exposes the `tab_size` of Zed's language settings, so the
+//! line length and indentation style are read from the `.editorconfig` at the root of the
+//! worktree (if any), which takes precedence just as it does in Zed itself.
+//!
+//! A `latexindent` local settings file is only generated if the `.editorconfig` sets the
+//! indentation, since it replaces `latexindent`'s own lookup of `localSettings.yaml`.
+
+use super::work_files;
+use zed_extension_api as zed;
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct FormattingPreferences {
+    pub line_length: Option<u32>,
+    pub indent: Option<Indent>,
+    /// Absolute path to a generated `latexindent` local settings file applying the
+    /// indentation, if set by the `.editorconfig`.
+    pub latexindent_local: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Indent {
+    Tabs,
+    Spaces(u32),
+}
+
+impl FormattingPreferences {
+    /// Reads the formatting preferences for LaTeX documents in the worktree, and writes the
+    /// corresponding `latexindent` local settings file (if any).
+    ///
+    /// Zed's `tab_size` is only used for the size of the indentation when the `.editorconfig`
+    /// sets `indent_style = space` without a size.
+    pub fn for_worktree(worktree: &zed::Worktree) -> Self {
+        let tab_size = zed::settings::LanguageSettings::for_worktree(Some("LaTeX"), worktree)
+            .ok()
+            .map(|settings| settings.tab_size.get());
+        let mut preferences = worktree
+            .read_text_file(".editorconfig")
+            .map(|contents| Self::from_editorconfig(&contents, tab_size))
+            .unwrap_or_default();
+        preferences.latexindent_local = preferences.latexindent_settings().and_then(|yaml| {
+            work_files::write(&worktree.root_path(), "latexindent-zed", "yaml", &yaml)
+        });
+        preferences
+    }
+
+    /// Parses the properties of an `.editorconfig` file applying to `.tex` files.
+    /// Later sections take precedence over earlier ones, as in the EditorConfig specification.
+    fn from_editorconfig(contents: &str, tab_size: Option<u32>) -> Self {
+        let mut applies = false;
+        let mut max_line_length = None;
+        let mut indent_style = None;
+        let mut indent_size = None;
+        let mut tab_width = None;
+        for line in contents.lines().map(str::trim) {
+            if line.starts_with('[') && line.ends_with(']') {
+                applies = section_applies_to_tex(&line[1..line.len() - 1]);
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            if !applies {
+                continue;
+            }
+            let value = value.trim().to_lowercase();
+            match key.trim().to_lowercase().as_str() {
+                "max_line_length" => max_line_length = Some(value.parse().ok()),
+                "indent_style" => indent_style = Some(value),
+                "indent_size" => indent_size = value.parse().ok(),
+                "tab_width" => tab_width = value.parse().ok(),
+                _ => {}
+            }
+        }
+        let indent = match indent_style.as_deref() {
+            Some("tab") => Some(Indent::Tabs),
+            Some("space") => indent_size.or(tab_width).or(tab_size).map(Indent::Spaces),
+            _ => indent_size.map(Indent::Spaces),
+        };
+        FormattingPreferences {
+            line_length: max_line_length.flatten(),
+            indent,
+            latexindent_local: None,
+        }
+    }
+
+    /// Contents of the `latexindent` local settings file for these preferences.
+    fn latexindent_settings(&self) -> Option<String> {
+        let mut yaml = String::new();
+        match self.indent? {
+            Indent::Tabs => yaml.push_str("defaultIndent: \"\\t\"\n"),
+            Indent::Spaces(size) => yaml.push_str(&format!(
+                "defaultIndent: \"{}\"\n",
+                " ".repeat(size as usize)
+            )),
+        }
+        if let Some(line_length) = self.line_length {
+            // Only takes effect when `latexindent.modifyLineBreaks` is enabled.
+            yaml.push_str(&format!(
+                "modifyLineBreaks:\n  textWrapOptions:\n    columns: {line_length}\n"
+            ));
+        }
+        Some(yaml)
+    }
+}
+
+/// Whether an `.editorconfig` section glob matches `.tex` files.
+/// Only the common forms `*`, `*.tex` and `*.{tex,...}` are supported.
+fn section_applies_to_tex(glob: &str) -> bool {
+    let glob = glob.trim_start_matches("**/");
+    match glob {
+        "*" | "*.tex" => true,
+        _ => glob
+            .strip_prefix("*.{")
+            .and_then(|extensions| extensions.strip_suffix('}'))
+            .is_some_and(|extensions| extensions.split(',').any(|ext| ext.trim() == "tex")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_editorconfig_sections_for_tex_files() {
+        let editorconfig = "\
+root = true
+
+[*]
+indent_style = space
+indent_size = 2
+max_line_length = 120
+
+[*.py]
+indent_size = 4
+
+[*.{tex,sty}]
+max_line_length = 100
+";
+        let preferences = FormattingPreferences::from_editorconfig(editorconfig, Some(4));
+        assert_eq!(preferences.line_length, Some(100));
+        assert_eq!(preferences.indent, Some(Indent::Spaces(2)));
+    }
+
+    #[test]
+    fn test_editorconfig_tabs_and_line_length_off() {
+        let editorconfig = "[*.tex]\nindent_style = tab\nmax_line_length = off\n";
+        let preferences = FormattingPreferences::from_editorconfig(editorconfig, Some(4));
+        assert_eq!(preferences.line_length, None);
+        assert_eq!(preferences.indent, Some(Indent::Tabs));
+    }
+
+    #[test]
+    fn test_editorconfig_without_indentation() {
+        let preferences =
+            FormattingPreferences::from_editorconfig("[*]\nmax_line_length = 80\n", Some(4));
+        assert_eq!(preferences.line_length, Some(80));
+        assert_eq!(preferences.indent, None);
+        assert!(preferences.latexindent_settings().is_none());
+    }
+
+    #[test]
+    fn test_latexindent_settings() {
+        let preferences = FormattingPreferences {
+            line_length: Some(100),
+            indent: Some(Indent::Spaces(2)),
+            latexindent_local: None,
+        };
+        assert_eq!(
+            preferences.latexindent_settings().unwrap(),
+            "defaultIndent: \"  \"\nmodifyLineBreaks:\n  textWrapOptions:\n    columns: 100\n"
+        );
+        assert!(FormattingPreferences::default()
+            .latexindent_settings()
+            .is_none());
+    }
+}
@@ -0,0 +1,40 @@
+//! Builds of R noweb (`.Rnw`) documents: the R code chunks are woven into a `.tex` document
+//! with knitr (or Sweave, for documents using `\SweaveOpts` or the `Sweave` package), which
+//! is then built as usual.
+//!
+//! Both write concordance data mapping the lines of the `.tex` document back to the `.Rnw`
+//! document. If the `patchDVI` R package is installed, it is used to patch the SyncTeX data
+//! accordingly, so that forward and inverse search work with the `.Rnw` document.
+//!
+//! Any saved `.Rnw` file is built, so the default `latexmk` build always includes this code,
+//! which leaves other documents alone.
+
+/// Perl code for `latexmk -e` weaving `.Rnw` documents, and building the woven `.tex`
+/// document instead.
+pub const LATEXMK_SNIPPET: &str = concat!(
+    "if ($ARGV[-1] =~ /\\.[Rr]nw$/) { ",
+    "my ($dir, $file) = $ARGV[-1] =~ m{^(.*[/\\\\])?([^/\\\\]*)$}; ",
+    "chdir $dir if defined $dir; ",
+    "my $sweave = 0; ",
+    "if (open(my $fh, '<', $file)) { local $/; my $contents = <$fh>; ",
+    "$sweave = $contents =~ /\\\\SweaveOpts|\\\\usepackage(\\[[^\\]]*\\])?\\{Sweave\\}/; close($fh); } ",
+    "my $weave = $sweave ? 'utils::Sweave(commandArgs(TRUE)[1], concordance = TRUE)' ",
+    ": 'knitr::opts_knit$set(concordance = TRUE); knitr::knit(commandArgs(TRUE)[1])'; ",
+    "exit 1 if system('Rscript', '-e', $weave, $file) != 0; ",
+    "($ARGV[-1] = $file) =~ s/\\.[Rr]nw$/.tex/; ",
+    "for ($pdflatex, $lualatex, $xelatex) { ",
+    "$_ .= q{ && Rscript -e \"if (requireNamespace('patchDVI', quietly = TRUE)) try(patchDVI::patchSynctex(commandArgs(TRUE)[1]))\" %B}; ",
+    "} ",
+    "}",
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snippet_weaves_and_patches_synctex() {
+        assert!(LATEXMK_SNIPPET.contains("knitr::knit"));
+        assert!(LATEXMK_SNIPPET.contains("patchDVI::patchSynctex"));
+    }
+}
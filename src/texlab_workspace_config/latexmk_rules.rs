@@ -0,0 +1,70 @@
+//! Extra `latexmk` rules running the tools some packages need between the passes of the TeX
+//! engine (e.g. `pythontex` for the `pythontex` package, `asy` for the `asymptote` package),
+//! written to a latexmkrc generated for the worktree and passed to the default build with `-r`
+//! when the detected root document loads these packages.
+//!
+//! The rules only add custom dependencies, so the engine commands (and their SyncTeX output)
+//! are left untouched.
+
+use super::work_files;
+
+/// `latexmk` rules (Perl code) needed by documents loading each package.
+const RULES: &[(&str, &str)] = &[
+    (
+        "pythontex",
+        r#"# PythonTeX: run the code extracted by the first pass, before the next one.
+add_cus_dep('pytxcode', 'tex', 0, 'pythontex');
+sub pythontex { return system('pythontex', $_[0]); }
+push @generated_exts, 'pytxcode';
+"#,
+    ),
+    (
+        "asymptote",
+        r#"# Asymptote: compile the figures written by the first pass, before the next one.
+sub asy { return system('asy', '-o', $_[0], $_[0]); }
+add_cus_dep('asy', 'eps', 0, 'asy');
+add_cus_dep('asy', 'pdf', 0, 'asy');
+add_cus_dep('asy', 'tex', 0, 'asy');
+"#,
+    ),
+    (
+        "sagetex",
+        r#"# SageTeX: run the Sage script written by the first pass, before the next one.
+add_cus_dep('sage', 'sout', 0, 'makesout');
+$hash_calc_ignore_pattern{'sage'} = '^( _st_.goboom|print .SageT)';
+sub makesout { return system('sage', "$_[0].sage"); }
+push @generated_exts, 'sage', 'sout';
+"#,
+    ),
+];
+
+/// Writes the rules needed by `packages` (if any) to the latexmkrc generated for the worktree
+/// at `worktree_root`, returning its absolute path.
+pub fn write(worktree_root: &str, packages: &[String]) -> Option<String> {
+    work_files::write(worktree_root, "latexmkrc-zed", "pl", &contents(packages)?)
+}
+
+fn contents(packages: &[String]) -> Option<String> {
+    let rules = RULES
+        .iter()
+        .filter(|(package, _)| packages.iter().any(|loaded| loaded == package))
+        .map(|(_, rules)| *rules)
+        .collect::<Vec<_>>();
+    (!rules.is_empty()).then(|| rules.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rules_for_loaded_packages() {
+        assert!(contents(&["amsmath".to_string()]).is_none());
+        let rules = contents(&["sagetex".to_string(), "pythontex".to_string()]).unwrap();
+        assert!(rules.starts_with("# PythonTeX"));
+        assert!(rules.contains("add_cus_dep('sage', 'sout', 0, 'makesout');"));
+        assert!(contents(&["asymptote".to_string()])
+            .unwrap()
+            .contains("add_cus_dep('asy', 'pdf', 0, 'asy');"));
+    }
+}
@@ -0,0 +1,114 @@
+//! Settings of the project's latexmkrc which the default build should not override, and
+//! which tell texlab where `latexmk` puts its output.
+//!
+//! Only literal assignments are understood, e.g.
+//! ```perl
+//! $pdf_mode = 4;
+//! $out_dir = 'build';
+//! @default_files = ('thesis.tex');
+//! ```
+
+use zed_extension_api as zed;
+
+/// `latexmk` configuration files of a project, in the order `latexmk` looks for them.
+const LATEXMKRC_FILES: &[&str] = &["latexmkrc", ".latexmkrc"];
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Latexmkrc {
+    /// Documents built by default, with the `.tex` extension `latexmk` would add.
+    pub default_files: Vec<String>,
+    pub pdf_mode: Option<u32>,
+    pub out_dir: Option<String>,
+    pub aux_dir: Option<String>,
+}
+
+impl Latexmkrc {
+    /// Reads the project's latexmkrc at the root of the worktree, if any.
+    pub fn for_worktree(worktree: &zed::Worktree) -> Option<Self> {
+        LATEXMKRC_FILES
+            .iter()
+            .find_map(|file| worktree.read_text_file(file).ok())
+            .map(|contents| Self::parse(&contents))
+    }
+
+    fn parse(contents: &str) -> Self {
+        let mut latexmkrc = Latexmkrc::default();
+        let statements = contents
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or_default())
+            .flat_map(|line| line.split(';'))
+            .filter_map(|statement| statement.split_once('='))
+            .map(|(variable, value)| (variable.trim(), value.trim()));
+        for (variable, value) in statements {
+            match variable {
+                "$pdf_mode" => latexmkrc.pdf_mode = value.parse().ok(),
+                "$out_dir" => latexmkrc.out_dir = string_literal(value),
+                "$aux_dir" => latexmkrc.aux_dir = string_literal(value),
+                "@default_files" => latexmkrc.default_files = list_literal(value),
+                _ => {}
+            }
+        }
+        latexmkrc
+    }
+}
+
+/// The value of a quoted Perl string without interpolation.
+fn string_literal(value: &str) -> Option<String> {
+    let quote = value.chars().next().filter(|c| matches!(c, '\'' | '"'))?;
+    let value = value.strip_prefix(quote)?.strip_suffix(quote)?;
+    (!value.contains(['$', '@'])).then(|| value.to_string())
+}
+
+/// The files of a Perl list, e.g. `('main.tex', 'other')` or `qw(main other)`.
+fn list_literal(value: &str) -> Vec<String> {
+    let value = value.strip_prefix("qw").unwrap_or(value).trim();
+    value
+        .trim_start_matches('(')
+        .split(')')
+        .next()
+        .unwrap_or_default()
+        .split([',', ' '])
+        .map(|file| file.trim_matches(['\'', '"']))
+        .filter(|file| !file.is_empty())
+        .map(|file| {
+            if file.contains('.') {
+                file.to_string()
+            } else {
+                format!("{file}.tex")
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_latexmkrc() {
+        let latexmkrc = Latexmkrc::parse(
+            "# Build thesis with LuaLaTeX\n$pdf_mode = 4; $out_dir = 'build';\n$aux_dir = \"$out_dir/aux\";\n@default_files = ('thesis.tex', 'slides.tex');\n",
+        );
+        assert_eq!(
+            latexmkrc,
+            Latexmkrc {
+                default_files: vec!["thesis.tex".to_string(), "slides.tex".to_string()],
+                pdf_mode: Some(4),
+                out_dir: Some("build".to_string()),
+                aux_dir: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_default_files_get_tex_extension() {
+        assert_eq!(
+            Latexmkrc::parse("@default_files=qw(main);").default_files,
+            ["main.tex"]
+        );
+        assert_eq!(
+            Latexmkrc::parse("# @default_files = ('old.tex');\n"),
+            Latexmkrc::default()
+        );
+    }
+}
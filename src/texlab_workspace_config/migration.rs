@@ -0,0 +1,306 @@
+//! Migration of deprecated texlab settings keys (e.g. from older wiki examples or dotfiles)
+//! to their current equivalents, before the settings are validated.
+//!
+//! Every migration is reported, so that old configurations keep working while users
+//! learn the new names. Settings whose meaning changed are reported too.
+
+use zed_extension_api::serde_json::{Map, Value};
+
+/// Keys renamed (or moved) by texlab, as `(old path, new path)`.
+///
+/// This includes the `latex` and `bibtex` sections of texlab 2, and the `latexindent`
+/// section outside of the `texlab` one.
+const RENAMED: &[(&str, &str)] = &[
+    ("texlab.auxDirectory", "texlab.build.auxDirectory"),
+    ("latex.build.executable", "texlab.build.executable"),
+    ("latex.build.args", "texlab.build.args"),
+    ("latex.build.onSave", "texlab.build.onSave"),
+    (
+        "latex.build.forwardSearchAfter",
+        "texlab.build.forwardSearchAfter",
+    ),
+    (
+        "latex.forwardSearch.executable",
+        "texlab.forwardSearch.executable",
+    ),
+    ("latex.forwardSearch.args", "texlab.forwardSearch.args"),
+    ("latex.lint.onSave", "texlab.chktex.onOpenAndSave"),
+    ("latex.lint.onChange", "texlab.chktex.onEdit"),
+    ("bibtex.formatting.lineLength", "texlab.formatterLineLength"),
+    (
+        "texlab.bibtexFormatting.lineLength",
+        "texlab.formatterLineLength",
+    ),
+    (
+        "texlab.bibtexFormatting.formatter",
+        "texlab.bibtexFormatter",
+    ),
+    ("latexindent.local", "texlab.latexindent.local"),
+    (
+        "latexindent.modifyLineBreaks",
+        "texlab.latexindent.modifyLineBreaks",
+    ),
+];
+
+/// Keys removed by texlab, as `(path, replacement advice)`.
+const REMOVED: &[(&str, &str)] = &[
+    (
+        "texlab.build.isContinuous",
+        "continuous builds are no longer supported, use `texlab.build.onSave` instead",
+    ),
+    (
+        "texlab.rootDirectory",
+        "create a `.texlabroot` file in the root directory instead",
+    ),
+];
+
+/// Rewrites deprecated keys of `lsp.texlab.settings` to their current equivalents,
+/// returning the migrated settings along with a description of each migration.
+///
+/// Keys written in dotted form (as in VS Code settings), e.g. `"texlab.build.onSave": true`,
+/// are nested first.
+pub fn migrate(settings: Value) -> (Value, Vec<String>) {
+    let mut migrations = Vec::new();
+    let mut settings = nest_dotted_keys(settings, "", &mut migrations);
+
+    for (old_path, new_path) in RENAMED {
+        if let Some(value) = take(&mut settings, old_path) {
+            if get(&settings, new_path).is_some() {
+                migrations.push(format!(
+                    "`{old_path}` is deprecated and was ignored since `{new_path}` is also set"
+                ));
+            } else {
+                insert(&mut settings, new_path, value);
+                migrations.push(format!(
+                    "`{old_path}` is deprecated, it was migrated to `{new_path}`"
+                ));
+            }
+        }
+    }
+    for (path, advice) in REMOVED {
+        if take(&mut settings, path).is_some() {
+            migrations.push(format!(
+                "`{path}` was removed from texlab and was ignored: {advice}"
+            ));
+        }
+    }
+    for section in ["latex", "bibtex", "latexindent", "texlab.bibtexFormatting"] {
+        remove_if_empty(&mut settings, section);
+    }
+    if get(&settings, "texlab.forwardSearch")
+        .is_some_and(|value| value == &Value::Object(Map::new()))
+    {
+        migrations.push(
+            "`texlab.forwardSearch` is empty, which no longer disables forward search autoconfiguration: it is filled in for the detected previewer, set it to `null` to disable this".to_string(),
+        );
+    }
+
+    (settings, migrations)
+}
+
+fn nest_dotted_keys(value: Value, path: &str, migrations: &mut Vec<String>) -> Value {
+    let Value::Object(map) = value else {
+        return value;
+    };
+    let mut nested = Value::Object(Map::new());
+    for (key, field) in map {
+        let field_path = if path.is_empty() {
+            key.clone()
+        } else {
+            format!("{path}.{key}")
+        };
+        let field = nest_dotted_keys(field, &field_path, migrations);
+        if key.contains('.') {
+            migrations.push(format!(
+                "`\"{key}\"` was written as a dotted key, it was nested as `{field_path}`"
+            ));
+        }
+        insert(&mut nested, &key, field);
+    }
+    nested
+}
+
+/// Removes the objects left empty within the object at `path` (including itself), e.g. once
+/// the keys of a legacy section have been migrated.
+fn remove_if_empty(value: &mut Value, path: &str) {
+    /// Removes the empty objects within `value`, returning whether it is itself empty.
+    fn prune(value: &mut Value) -> bool {
+        let Value::Object(map) = value else {
+            return false;
+        };
+        map.retain(|_, field| !prune(field));
+        map.is_empty()
+    }
+    let section = path.split('.').try_fold(&mut *value, |value, key| {
+        value.as_object_mut()?.get_mut(key)
+    });
+    if section.is_some_and(prune) {
+        take(value, path);
+    }
+}
+
+fn get<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .try_fold(value, |value, key| value.as_object()?.get(key))
+}
+
+fn take(value: &mut Value, path: &str) -> Option<Value> {
+    let (parent_path, key) = match path.rsplit_once('.') {
+        Some((parent_path, key)) => (Some(parent_path), key),
+        None => (None, path),
+    };
+    let parent = match parent_path {
+        Some(parent_path) => parent_path
+            .split('.')
+            .try_fold(value, |value, key| value.as_object_mut()?.get_mut(key))?,
+        None => value,
+    };
+    parent.as_object_mut()?.remove(key)
+}
+
+/// Inserts `new_value` at `path`, creating intermediate objects as needed and merging
+/// objects already present at `path`.
+fn insert(value: &mut Value, path: &str, new_value: Value) {
+    let mut current = value;
+    for key in path.split('.') {
+        if !current.is_object() {
+            *current = Value::Object(Map::new());
+        }
+        current = current
+            .as_object_mut()
+            .expect("just made into an object")
+            .entry(key)
+            .or_insert(Value::Null);
+    }
+    merge(current, new_value);
+}
+
+fn merge(existing: &mut Value, new_value: Value) {
+    match (existing, new_value) {
+        (Value::Object(existing), Value::Object(new_map)) => {
+            for (key, field) in new_map {
+                merge(existing.entry(key).or_insert(Value::Null), field);
+            }
+        }
+        (existing, new_value) => *existing = new_value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zed_extension_api::serde_json::json;
+
+    #[test]
+    fn test_renamed_keys_are_migrated() {
+        let (settings, migrations) = migrate(json!({
+            "texlab": { "auxDirectory": "build", "build": { "onSave": true } }
+        }));
+        assert_eq!(
+            settings,
+            json!({ "texlab": { "build": { "onSave": true, "auxDirectory": "build" } } })
+        );
+        assert_eq!(
+            migrations,
+            ["`texlab.auxDirectory` is deprecated, it was migrated to `texlab.build.auxDirectory`"]
+        );
+    }
+
+    #[test]
+    fn test_legacy_sections_are_migrated() {
+        let (settings, migrations) = migrate(json!({
+            "latex": { "lint": { "onSave": true, "onChange": false } },
+            "bibtex": { "formatting": { "lineLength": 100 } },
+            "latexindent": { "local": "indent.yaml", "modifyLineBreaks": true },
+            "texlab": { "bibtexFormatting": { "formatter": "latexindent" } }
+        }));
+        assert_eq!(
+            settings,
+            json!({
+                "texlab": {
+                    "chktex": { "onOpenAndSave": true, "onEdit": false },
+                    "formatterLineLength": 100,
+                    "latexindent": { "local": "indent.yaml", "modifyLineBreaks": true },
+                    "bibtexFormatter": "latexindent"
+                }
+            })
+        );
+        assert_eq!(migrations.len(), 6);
+        assert!(migrations.contains(
+            &"`latex.lint.onSave` is deprecated, it was migrated to `texlab.chktex.onOpenAndSave`"
+                .to_string()
+        ));
+    }
+
+    #[test]
+    fn test_unmigrated_keys_of_legacy_sections_are_kept() {
+        let (settings, _) = migrate(json!({
+            "latex": { "build": { "onSave": true }, "rootDirectory": "." }
+        }));
+        assert_eq!(
+            settings,
+            json!({
+                "latex": { "rootDirectory": "." },
+                "texlab": { "build": { "onSave": true } }
+            })
+        );
+    }
+
+    #[test]
+    fn test_current_key_takes_precedence_over_deprecated_one() {
+        let (settings, migrations) = migrate(json!({
+            "texlab": { "auxDirectory": "old", "build": { "auxDirectory": "new" } }
+        }));
+        assert_eq!(
+            settings,
+            json!({ "texlab": { "build": { "auxDirectory": "new" } } })
+        );
+        assert_eq!(migrations.len(), 1);
+        assert!(migrations[0].contains("was ignored"));
+    }
+
+    #[test]
+    fn test_removed_keys_are_dropped() {
+        let (settings, migrations) = migrate(json!({
+            "texlab": { "build": { "isContinuous": true, "onSave": true } }
+        }));
+        assert_eq!(
+            settings,
+            json!({ "texlab": { "build": { "onSave": true } } })
+        );
+        assert!(migrations[0].starts_with("`texlab.build.isContinuous` was removed"));
+    }
+
+    #[test]
+    fn test_empty_forward_search_is_reported() {
+        let settings = json!({ "texlab": { "forwardSearch": {} } });
+        let (migrated, migrations) = migrate(settings.clone());
+        assert_eq!(migrated, settings);
+        assert!(migrations[0].contains("set it to `null` to disable this"));
+    }
+
+    #[test]
+    fn test_dotted_keys_are_nested() {
+        let (settings, migrations) = migrate(json!({
+            "texlab.build.onSave": true,
+            "texlab": { "build": { "executable": "latexmk" }, "chktex.onEdit": true }
+        }));
+        assert_eq!(
+            settings,
+            json!({
+                "texlab": {
+                    "build": { "executable": "latexmk", "onSave": true },
+                    "chktex": { "onEdit": true }
+                }
+            })
+        );
+        assert_eq!(migrations.len(), 2);
+    }
+
+    #[test]
+    fn test_current_settings_are_untouched() {
+        let settings = json!({ "texlab": { "build": { "auxDirectory": "build" } } });
+        assert_eq!(migrate(settings.clone()), (settings, vec![]));
+        assert_eq!(migrate(Value::Null), (Value::Null, vec![]));
+    }
+}
@@ -3,20 +3,70 @@
 //! It handles:
 //! - Retrieving Texlab LSP settings for a given worktree
 //! - Modifying settings based on detected PDF previewers
-//! - Adding forward search settings when appropriate (never overriding user-provided settings)
-//! - Providing default build command if not provided
+//! - Adding forward search settings when appropriate (merged field by field with user-provided settings)
+//! - Providing default build command if not provided (with `latexmk`, `tectonic`, `arara`,
+//!   `make` or `just`, following the project configuration, the project's latexmkrc, the
+//!   detected root document and its magic comments)
+//! - Deriving formatter settings from the editor settings (tab size, `.editorconfig`)
+//! - Expanding the chosen diagnostics presets into ignored patterns
+//! - Enabling ChkTeX when it is available and configured for the project
+//! - Picking the formatters depending on whether `latexindent` is available
+//! - Migrating deprecated settings keys to their current equivalents
+//! - Validating the user-provided settings, reporting (and dropping) unknown keys and
+//!   rejecting wrongly typed values
+//! - Describing the effective settings along with the origin of each value
 //!
 //! The settings modifications are focused on enabling build-on-save and forward search
 //! features when a PDF previewer is detected, while being careful not to override any
-//! existing user configurations.
+//! existing user configurations. Each of them can be switched off with the `autoconfig`
+//! extension option.
 
+mod build_profiles;
+mod diagnostics_presets;
+mod docstrip;
+pub mod formatting;
+mod knitr;
+mod latexmk_rules;
+mod latexmkrc;
+mod migration;
+mod origins;
 pub mod preview_presets;
+pub mod root_document;
+// Only needed to (re)generate the shipped JSON Schema, see `schemas/texlab-settings.json`.
+#[cfg(test)]
+mod schema;
+pub mod tool_detection;
 mod types;
+mod validation;
+mod work_files;
 
+use crate::extension_options::{Autoconfig, BuildBackend, ExtensionOptions, ShellEscape};
+use crate::project_config::{ProjectConfig, PROJECT_CONFIG_FILE};
 use crate::zed_command::CommandName;
+use formatting::FormattingPreferences;
+use origins::Origin;
 use preview_presets::Preview;
-use types::{TexlabBuildSettings, TexlabHoverSettings, TexlabSettings, WorkspaceSettings};
-use zed_extension_api::serde_json::{from_value, Value};
+use root_document::RootDocument;
+use tool_detection::{BuildFile, DetectedTools};
+use types::{
+    TexlabBuildSettings, TexlabChktexSettings, TexlabDiagnosticsSettings, TexlabFormatter,
+    TexlabHoverSettings, TexlabLatexindentSettings, TexlabSettings, WorkspaceSettings,
+};
+use zed_extension_api::serde_json::{from_value, json, to_value, Value};
+
+/// Everything known about the worktree, other than the user's texlab settings, which affects
+/// the settings filled in by the extension.
+pub struct Context<'a> {
+    /// Detected PDF previewer, if any
+    pub previewer: &'a Option<Preview>,
+    /// Command to launch Zed for inverse search
+    pub zed_command: &'a CommandName,
+    pub options: &'a ExtensionOptions,
+    pub formatting: &'a FormattingPreferences,
+    pub project: &'a ProjectConfig,
+    pub document: &'a RootDocument,
+    pub tools: &'a DetectedTools,
+}
 
 /// Retrieves and potentially modifies the texlab LSP settings for a given worktree.
 ///
@@ -26,86 +76,684 @@ use zed_extension_api::serde_json::{from_value, Value};
 /// Returns either:
 /// - The original settings if no previewer is detected
 /// - Modified settings with forward search and build settings if a previewer exists
-/// - Error string if settings deserialization fails (which means the settings are invalid)
+/// - Error string listing every wrongly typed value, or describing why deserialization failed
+///
+/// The settings come along with notes for the user: migrations of deprecated settings keys,
+/// dropped unknown keys and hints (listed by `/texlab-config`).
 pub fn get(
-    previewer: &Option<Preview>,
-    zed_command: CommandName,
+    context: &Context,
+    lsp_texlab_settings: Value,
+) -> Result<(WorkspaceSettings, Vec<String>), String> {
+    let (texlab_settings, mut notes) = resolve(context, lsp_texlab_settings, &mut |_, _| {})?;
+    notes.extend(shell_escape_hint(context));
+    notes.extend(arara_synctex_hint(context));
+    Ok((
+        WorkspaceSettings {
+            texlab: Some(texlab_settings),
+        },
+        notes,
+    ))
+}
+
+/// Describes the effective texlab settings (as returned by [`get`]), along with where each
+/// value came from: the user, an extension default, the editor settings, or the detected
+/// previewer/Zed command.
+pub fn describe(context: &Context, lsp_texlab_settings: Value) -> Result<String, String> {
+    let mut stages = Vec::new();
+    let (_, notes) = resolve(context, lsp_texlab_settings, &mut |origin, settings| {
+        stages.push((
+            origin,
+            json!({ "texlab": to_value(settings).unwrap_or_default() }),
+        ))
+    })?;
+    let mut description = origins::describe(
+        context.previewer.as_ref().map(Preview::name),
+        context.zed_command,
+        &stages,
+    );
+    if !notes.is_empty() {
+        description.push_str("\n\nMigrated or ignored settings:");
+        for note in notes {
+            description.push_str(&format!("\n  - {note}"));
+        }
+    }
+    for hint in shell_escape_hint(context)
+        .into_iter()
+        .chain(arara_synctex_hint(context))
+    {
+        description.push_str(&format!("\n\nNote: {hint}"));
+    }
+    Ok(description)
+}
+
+/// Packages running tools (`pygmentize`, `inkscape`, `gnuplot`) which restricted shell escape
+/// does not allow.
+const FULL_SHELL_ESCAPE_PACKAGES: &[&str] = &["minted", "svg", "gnuplottex"];
+
+/// Suggests opting in to shell escape if the root document loads packages needing it, since
+/// it is never enabled without the user's consent, or points out that the project
+/// configuration cannot enable it.
+fn shell_escape_hint(context: &Context) -> Option<String> {
+    if context.project.ignored_shell_escape.is_some() {
+        return Some(format!(
+            "`shell_escape` in {PROJECT_CONFIG_FILE} is ignored, set the `shell_escape` extension option (`lsp.texlab.initialization_options`) instead"
+        ));
+    }
+    let packages = &context.document.shell_escape_packages;
+    (!packages.is_empty() && context.options.shell_escape.is_none()).then(|| {
+        let level = if packages
+            .iter()
+            .any(|package| FULL_SHELL_ESCAPE_PACKAGES.contains(&package.as_str()))
+        {
+            "\"full\""
+        } else {
+            "\"restricted\" (or \"full\" if your TeX distribution does not allow the tools it runs)"
+        };
+        format!(
+            "the root document loads packages which need shell escape ({}), set the `shell_escape` extension option to {level} (in `lsp.texlab.initialization_options`) to allow it, or to \"off\" to silence this note",
+            packages.join(", ")
+        )
+    })
+}
+
+/// Suggests enabling SyncTeX in the arara directives of the root document if it is built with
+/// arara without it, since forward search needs it.
+fn arara_synctex_hint(context: &Context) -> Option<String> {
+    let document = context.document;
+    let backend = build_backend(context.project, document, context.options, context.tools);
+    (backend == BuildBackend::Arara && document.arara && !document.arara_synctex).then(|| {
+        "the root document is built with arara, but its directives do not enable SyncTeX, add `synctex: yes` to the engine directive (e.g. `% arara: pdflatex: { synctex: yes }`) for forward search to work".to_string()
+    })
+}
+
+/// Migrates and validates the user-provided settings and fills in the extension's defaults
+/// and previewer settings, calling `record` with the settings after each of these stages.
+///
+/// Returns the resulting settings along with a description of each migration performed and
+/// each unknown key dropped.
+fn resolve(
+    context: &Context,
     lsp_texlab_settings: Value,
-) -> Result<WorkspaceSettings, String> {
+    record: &mut dyn FnMut(Origin, &TexlabSettings),
+) -> Result<(TexlabSettings, Vec<String>), String> {
+    let autoconfig = &context.options.autoconfig;
+
+    let (lsp_texlab_settings, mut notes) = migration::migrate(lsp_texlab_settings);
+    let problems = validation::validate(
+        &lsp_texlab_settings,
+        &validation::workspace_settings_schema(),
+        "",
+    );
+    if !problems.invalid.is_empty() {
+        return Err(validation::report(&problems.invalid));
+    }
+    // Unknown keys are dropped by the deserialization below, rather than discarding the whole
+    // configuration.
+    notes.extend(
+        problems
+            .unknown
+            .into_iter()
+            .map(|problem| format!("{problem}, it was ignored")),
+    );
+    // An unknown build profile is left out of the build command (see `latexmk_build`).
+    notes.extend(
+        build_profile(context.project, context.options)
+            .and_then(|profile| {
+                build_profiles::profile_args(profile, &context.options.build_profiles, None).err()
+            })
+            .map(|problem| format!("{problem}, it was ignored")),
+    );
+
     let provided_texlab_settings = from_value::<Option<WorkspaceSettings>>(lsp_texlab_settings)
         .map_err(|err| err.to_string())? // Do not silently pass settings on when deserialization fails anymore
         .unwrap_or_default()
         .texlab
         .unwrap_or_default();
+    record(Origin::User, &provided_texlab_settings);
 
-    let texlab_settings_with_defaults =
-        add_hover_default(add_build_default(provided_texlab_settings));
+    let mut texlab_settings_with_defaults = provided_texlab_settings;
+    if autoconfig.build {
+        texlab_settings_with_defaults = add_build_default(
+            context.project,
+            context.document,
+            context.options,
+            context.tools,
+            texlab_settings_with_defaults,
+        );
+    }
+    if autoconfig.hover {
+        texlab_settings_with_defaults = add_hover_default(texlab_settings_with_defaults);
+    }
+    let (settings_with_presets, unknown_presets) =
+        add_diagnostics_presets(context.options, texlab_settings_with_defaults);
+    texlab_settings_with_defaults = settings_with_presets;
+    notes.extend(
+        unknown_presets
+            .into_iter()
+            .map(|problem| format!("{problem}, it was ignored")),
+    );
+    record(Origin::ExtensionDefault, &texlab_settings_with_defaults);
 
-    let settings_with_previewer = if let Some(ref previewer) = previewer {
-        add_preview(&previewer, zed_command, texlab_settings_with_defaults)
+    texlab_settings_with_defaults = add_project_directories(
+        context.project,
+        context.tools,
+        texlab_settings_with_defaults,
+    );
+    record(Origin::ProjectFile, &texlab_settings_with_defaults);
+
+    if autoconfig.chktex {
+        texlab_settings_with_defaults =
+            add_chktex_defaults(context.tools, texlab_settings_with_defaults);
+    }
+    if autoconfig.formatter {
+        texlab_settings_with_defaults =
+            add_latexindent_defaults(context.tools, texlab_settings_with_defaults);
+    }
+    record(Origin::DetectedTools, &texlab_settings_with_defaults);
+
+    if autoconfig.formatter {
+        texlab_settings_with_defaults =
+            add_formatter_defaults(context.formatting, texlab_settings_with_defaults);
+    }
+    record(Origin::EditorSettings, &texlab_settings_with_defaults);
+
+    let settings_with_previewer = if let Some(ref previewer) = context.previewer {
+        add_preview(
+            previewer,
+            context.zed_command,
+            autoconfig,
+            texlab_settings_with_defaults,
+        )
     } else {
         texlab_settings_with_defaults
     };
+    record(Origin::Detected, &settings_with_previewer);
 
-    Ok(WorkspaceSettings {
-        texlab: Some(settings_with_previewer),
-    })
+    Ok((settings_with_previewer, notes))
 }
 
-/// Add previewer related settings to have forward and inverse search set up (if possible),
-/// but only if the user has not provided forward search settings themselves.
+/// Add previewer related settings to have forward and inverse search set up (if possible).
+///
+/// Any forward search fields provided by the user take precedence over the preset for the
+/// detected previewer, field by field. Explicitly setting `forwardSearch` to `null`
+/// disables this autoconfiguration altogether.
 fn add_preview(
     previewer: &Preview,
-    zed_command: CommandName,
+    zed_command: &CommandName,
+    autoconfig: &Autoconfig,
     texlab_settings_with_defaults: TexlabSettings,
 ) -> TexlabSettings {
     match texlab_settings_with_defaults {
-        // User has provided forward search settings, do not override.
+        // User has explicitly disabled forward search, do not override.
         TexlabSettings {
-            forward_search: Some(_),
+            forward_search: Some(None),
             ..
         } => texlab_settings_with_defaults,
-        // User has not provided forward search settings, which
-        // can be filled in for detected previewer; and enable build-on-save
-        // and forward search after build unless explicitly disabled.
-        texlab_settings_without_forward_search => TexlabSettings {
-            forward_search: Some(previewer.create_preset(zed_command)),
-            build: Some(
-                texlab_settings_without_forward_search
-                    .build
-                    .unwrap_or_default()
-                    .switch_on_onsave_fields_if_not_set(),
-            ),
-            ..texlab_settings_without_forward_search
-        },
+        // Fill in whatever forward search settings the user has not provided
+        // for the detected previewer; and enable build-on-save and forward
+        // search after build unless explicitly disabled.
+        texlab_settings => {
+            let forward_search = if autoconfig.forward_search {
+                let preset = previewer.create_preset(zed_command);
+                Some(Some(match texlab_settings.forward_search {
+                    Some(Some(provided)) => provided.or(preset),
+                    _ => preset,
+                }))
+            } else {
+                texlab_settings.forward_search
+            };
+            let build = if autoconfig.on_save {
+                Some(
+                    texlab_settings
+                        .build
+                        .unwrap_or_default()
+                        .switch_on_onsave_fields_if_not_set(),
+                )
+            } else {
+                texlab_settings.build
+            };
+            TexlabSettings {
+                forward_search,
+                build,
+                ..texlab_settings
+            }
+        }
     }
 }
 
-fn add_build_default(input_settings: TexlabSettings) -> TexlabSettings {
-    match input_settings {
+/// Provides a default build command, with the build tool chosen by [`build_backend`].
+fn add_build_default(
+    project: &ProjectConfig,
+    document: &RootDocument,
+    options: &ExtensionOptions,
+    tools: &DetectedTools,
+    input_settings: TexlabSettings,
+) -> TexlabSettings {
+    let build = input_settings.build.unwrap_or_default();
+    if build.executable.is_some() {
+        return TexlabSettings {
+            build: Some(build),
+            ..input_settings
+        };
+    }
+    let target = build_target(project, options);
+    let default_build = match build_backend(project, document, options, tools) {
+        BuildBackend::Latexmk => latexmk_build(project, document, options, tools),
+        BuildBackend::Tectonic => tectonic_build(project, options, tools),
+        BuildBackend::Arara => arara_build(project, document),
+        BuildBackend::Make => make_build(tools.makefile.as_ref(), target),
+        BuildBackend::Just => just_build(tools.justfile.as_ref(), target),
+    };
+    TexlabSettings {
+        build: Some(TexlabBuildSettings {
+            executable: default_build.executable,
+            args: default_build.args,
+            pdf_directory: build.pdf_directory.or(default_build.pdf_directory),
+            filename: build.filename.or(default_build.filename),
+            ..build
+        }),
+        ..input_settings
+    }
+}
+
+/// The `make` or `just` target to build, from the project configuration or extension options
+/// (`pdf` by default).
+fn build_target<'a>(project: &'a ProjectConfig, options: &'a ExtensionOptions) -> &'a str {
+    project
+        .target
+        .as_deref()
+        .or(options.build_target.as_deref())
+        .unwrap_or("pdf")
+}
+
+/// The build profile chosen in the project configuration or extension options, if any.
+fn build_profile<'a>(project: &'a ProjectConfig, options: &'a ExtensionOptions) -> Option<&'a str> {
+    project
+        .profile
+        .as_deref()
+        .or(options.build_profile.as_deref())
+}
+
+/// The build tool chosen in the project configuration or extension options, or else:
+/// - `arara` if available and the root document has arara directives,
+/// - `make` or `just` if the project's `Makefile` or `justfile` has the build target,
+/// - `latexmk` unless only `tectonic` is available (or the worktree is a Tectonic project).
+fn build_backend(
+    project: &ProjectConfig,
+    document: &RootDocument,
+    options: &ExtensionOptions,
+    tools: &DetectedTools,
+) -> BuildBackend {
+    let target = build_target(project, options);
+    let has_target = |build_file: &Option<BuildFile>| {
+        build_file
+            .as_ref()
+            .is_some_and(|build_file| build_file.targets.iter().any(|name| name == target))
+    };
+    let detected = if tools.arara && document.arara {
+        BuildBackend::Arara
+    } else if has_target(&tools.makefile) {
+        BuildBackend::Make
+    } else if has_target(&tools.justfile) {
+        BuildBackend::Just
+    } else if tools.tectonic && (tools.tectonic_output.is_some() || !tools.latexmk) {
+        BuildBackend::Tectonic
+    } else {
+        BuildBackend::Latexmk
+    };
+    project
+        .backend
+        .or(options.build_backend)
+        .unwrap_or(detected)
+}
+
+/// `latexmk` build command, using the engine, output directory and root document from the
+/// project configuration if provided, and the arguments of the active build profile (if any).
+///
+/// Without an engine in the project configuration, the one requested by the `% !TEX program`
+/// magic comment of the document built is used, or else the preferred Unicode engine if it
+/// loads packages needing one (e.g. `fontspec`). Without a root document in the project
+/// configuration, the file being saved is built, unless it names its root document in a
+/// `% !TEX root` magic comment or another root document was detected. Docstrip `.ins` and
+/// `.dtx` files are built as such (if the document to build can be one, see [`may_build`]),
+/// and R noweb (`.Rnw`) documents are woven with knitr (or Sweave) before being built.
+///
+/// The output mode and directory set in the project's latexmkrc are left alone, unless the
+/// project configuration chooses otherwise. Shell escape is only allowed if chosen in the
+/// extension options. The tools needed by the root document's packages between passes
+/// (e.g. `pythontex`) are run through generated `latexmk` rules.
+fn latexmk_build(
+    project: &ProjectConfig,
+    document: &RootDocument,
+    options: &ExtensionOptions,
+    tools: &DetectedTools,
+) -> TexlabBuildSettings {
+    let latexmkrc = tools.latexmkrc.clone().unwrap_or_default();
+    let pdf_mode = if latexmkrc.pdf_mode.is_some() {
+        ""
+    } else {
+        "$pdf_mode = 1 unless $pdf_mode != 0; "
+    };
+    // texlab substitutes `%f` in every argument, so the Perl snippets passed with `-e` must not
+    // contain it: they find the document to build as the last argument instead.
+    let mut args: Vec<String> = vec![
+        "-e".into(),
+        format!(
+            "{pdf_mode}if ($ARGV[-1] =~ /\\.log$/ or $ARGV[-1] =~ /latexmkrc$/) {{ exit 0; }};"
+        ),
+    ];
+    args.extend(["-interaction=nonstopmode".into(), "-synctex=1".into()]);
+    let unicode_engine = document
+        .needs_unicode_engine
+        .then(|| options.unicode_engine.engine());
+    // The engine detected from the root document is only a fallback for when the document
+    // actually built cannot be read (see `root_document::select_engine`).
+    let detected_engine = match latexmkrc.pdf_mode {
+        Some(_) => None,
+        None => document.program.or(unicode_engine),
+    };
+    if let Some(engine) = project.engine.or(detected_engine) {
+        args.push(engine.latexmk_flag().into());
+    }
+    if let Some(ref output_directory) = project.output_directory {
+        args.push(format!("-outdir={output_directory}"));
+    }
+    if let Some(ref latexmk_rules) = document.latexmk_rules {
+        args.extend(["-r".into(), latexmk_rules.clone()]);
+    }
+    match options.shell_escape {
+        Some(ShellEscape::Full) => args.push("-shell-escape".into()),
+        Some(ShellEscape::Restricted) => args.push("-shell-restricted".into()),
+        Some(ShellEscape::Off) | None => {}
+    }
+    if let Some(profile) = build_profile(project, options) {
+        // An unknown profile is left out (and reported by `resolve`).
+        args.extend(
+            build_profiles::profile_args(
+                profile,
+                &options.build_profiles,
+                document.class.as_deref(),
+            )
+            .unwrap_or_default(),
+        );
+    }
+    // The engine, docstrip sources and R noweb weaving depend on the document to build, so they
+    // come once it is known.
+    let mut snippets = Vec::new();
+    if project.root.is_none() {
+        snippets.push(root_document::follow_root_magic_comment(
+            document.path.as_deref(),
+        ));
+    }
+    if may_build(project, document, tools.docstrip_sources, &["dtx", "ins"]) {
+        snippets.push(docstrip::LATEXMK_SNIPPET.into());
+    }
+    if project.engine.is_none() && latexmkrc.pdf_mode.is_none() {
+        snippets.push(root_document::select_engine(
+            options.unicode_engine.engine(),
+        ));
+    }
+    snippets.push(knitr::LATEXMK_SNIPPET.into());
+    args.extend(["-e".into(), snippets.join(" ")]);
+    args.push(project.root.clone().unwrap_or_else(|| "%f".into()));
+    // The documentation of a `.dtx` root, and the woven document of an `.Rnw` root, are named
+    // after it rather than after a `.tex` file.
+    let filename = project
+        .root
+        .as_deref()
+        .filter(|root| project.pdf.is_none() && has_extension(root, &["dtx", "Rnw", "rnw"]))
+        .map(pdf_name);
+    TexlabBuildSettings {
+        executable: Some("latexmk".to_string()),
+        args: Some(args),
+        filename,
+        ..Default::default()
+    }
+}
+
+/// Whether the document built by `latexmk` can have one of `extensions`: the root document
+/// from the project configuration if any, or else the file being saved, as long as the
+/// worktree `has_sources` with these extensions or the detected root document has one.
+fn may_build(
+    project: &ProjectConfig,
+    document: &RootDocument,
+    has_sources: bool,
+    extensions: &[&str],
+) -> bool {
+    match project.root {
+        Some(ref root) => has_extension(root, extensions),
+        None => {
+            has_sources
+                || document
+                    .path
+                    .as_deref()
+                    .is_some_and(|path| has_extension(path, extensions))
+        }
+    }
+}
+
+fn has_extension(path: &str, extensions: &[&str]) -> bool {
+    path.rsplit_once('.')
+        .is_some_and(|(_, extension)| extensions.contains(&extension))
+}
+
+/// Name of the PDF built from the document at `path`, whatever its extension.
+fn pdf_name(path: &str) -> String {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    let stem = name.rsplit_once('.').map_or(name, |(stem, _)| stem);
+    format!("{stem}.pdf")
+}
+
+/// `tectonic` build command: `tectonic -X build` for Tectonic projects (pointing texlab to
+/// the PDF of the first output), or else compiling the root document from the project
+/// configuration (or the file being saved) with SyncTeX, into the output directory from the
+/// project configuration.
+///
+/// Tectonic always uses XeTeX, so the engine and build profile are not taken into account.
+/// Tectonic has no restricted shell escape, so only full shell escape is passed on. Tectonic
+/// projects are built without SyncTeX, as `tectonic -X build` has no option to enable it, so
+/// forward search does not work for them.
+fn tectonic_build(
+    project: &ProjectConfig,
+    options: &ExtensionOptions,
+    tools: &DetectedTools,
+) -> TexlabBuildSettings {
+    if let Some(ref output) = tools.tectonic_output {
+        return TexlabBuildSettings {
+            executable: Some("tectonic".to_string()),
+            args: Some(vec!["-X".into(), "build".into(), "--keep-logs".into()]),
+            pdf_directory: Some(output.directory.clone()),
+            filename: Some(format!("{}.pdf", output.name)),
+            ..Default::default()
+        };
+    }
+    let mut args: Vec<String> = vec![
+        "-X".into(),
+        "compile".into(),
+        "--synctex".into(),
+        "--keep-logs".into(),
+        "--keep-intermediates".into(),
+    ];
+    if options.shell_escape == Some(ShellEscape::Full) {
+        args.extend(["-Z".into(), "shell-escape".into()]);
+    }
+    if let Some(ref output_directory) = project.output_directory {
+        args.extend(["--outdir".into(), output_directory.clone()]);
+    }
+    args.push(project.root.clone().unwrap_or_else(|| "%f".into()));
+    TexlabBuildSettings {
+        executable: Some("tectonic".to_string()),
+        args: Some(args),
+        ..Default::default()
+    }
+}
+
+/// `arara` build command, following the directives of the root document.
+///
+/// The directives decide the engine and its options, so they should enable SyncTeX for
+/// forward search (e.g. `% arara: pdflatex: { synctex: yes }`, see [`arara_synctex_hint`]).
+fn arara_build(project: &ProjectConfig, document: &RootDocument) -> TexlabBuildSettings {
+    let root = project.root.clone().or_else(|| document.path.clone());
+    TexlabBuildSettings {
+        executable: Some("arara".to_string()),
+        args: Some(vec![root.unwrap_or_else(|| "%f".into())]),
+        ..Default::default()
+    }
+}
+
+/// `make` build command for `target`, run from the directory of the project's `Makefile`
+/// (rather than the directory of the document being saved).
+fn make_build(makefile: Option<&BuildFile>, target: &str) -> TexlabBuildSettings {
+    let mut args = Vec::new();
+    if let Some((directory, _)) = makefile.and_then(|makefile| makefile.path.rsplit_once('/')) {
+        args.extend(["-C".to_string(), directory.to_string()]);
+    }
+    args.push(target.to_string());
+    TexlabBuildSettings {
+        executable: Some("make".to_string()),
+        args: Some(args),
+        ..Default::default()
+    }
+}
+
+/// `just` build command for the `target` recipe of the project's `justfile`.
+fn just_build(justfile: Option<&BuildFile>, target: &str) -> TexlabBuildSettings {
+    let mut args = Vec::new();
+    if let Some(justfile) = justfile {
+        args.extend(["--justfile".to_string(), justfile.path.clone()]);
+    }
+    args.push(target.to_string());
+    TexlabBuildSettings {
+        executable: Some("just".to_string()),
+        args: Some(args),
+        ..Default::default()
+    }
+}
+
+/// Points texlab to the output directory from the project configuration, or else to the
+/// output and auxiliary directories of the project's latexmkrc (to find the PDF for forward
+/// search, and the logs for diagnostics), and to the PDF produced by the build if named in the
+/// project configuration, unless the directories are provided by the user.
+fn add_project_directories(
+    project: &ProjectConfig,
+    tools: &DetectedTools,
+    input_settings: TexlabSettings,
+) -> TexlabSettings {
+    let latexmkrc = tools.latexmkrc.clone().unwrap_or_default();
+    let (output_directory, aux_directory) = match project.output_directory {
+        Some(ref output_directory) => (Some(output_directory.clone()), None),
+        None => (latexmkrc.out_dir, latexmkrc.aux_dir),
+    };
+    // `latexmk` writes the auxiliary files (including the log) into the output directory
+    // unless told otherwise.
+    let aux_directory = aux_directory.or_else(|| output_directory.clone());
+    let (pdf_directory, filename) =
+        match project.pdf.as_deref().and_then(|pdf| pdf.rsplit_once('/')) {
+            Some((directory, filename)) => {
+                (Some(directory.to_string()), Some(filename.to_string()))
+            }
+            None => (output_directory, None),
+        };
+    if aux_directory.is_none() && pdf_directory.is_none() {
+        return input_settings;
+    }
+    let build = input_settings.build.unwrap_or_default();
+    TexlabSettings {
+        build: Some(TexlabBuildSettings {
+            aux_directory: build.aux_directory.or_else(|| aux_directory.clone()),
+            log_directory: build.log_directory.or(aux_directory),
+            pdf_directory: build.pdf_directory.or(pdf_directory),
+            filename: build.filename.or(filename),
+            ..build
+        }),
+        ..input_settings
+    }
+}
+
+/// Enables ChkTeX (with the project's `.chktexrc`) when it is available and configured for
+/// the project, unless the user has set the corresponding settings.
+fn add_chktex_defaults(tools: &DetectedTools, input_settings: TexlabSettings) -> TexlabSettings {
+    let Some(ref chktexrc) = tools.chktexrc else {
+        return input_settings;
+    };
+    let chktex = input_settings.chktex.unwrap_or_default();
+    TexlabSettings {
+        chktex: Some(TexlabChktexSettings {
+            on_open_and_save: chktex.on_open_and_save.or(Some(true)),
+            // Linting on every edit is left to the user, as it can be slow on large documents.
+            on_edit: chktex.on_edit.or(Some(false)),
+            // ChkTeX only looks for `.chktexrc` in the working directory (and the home directory),
+            // which is the directory of the document rather than the project root.
+            additional_args: chktex
+                .additional_args
+                .or_else(|| Some(vec!["-l".to_string(), chktexrc.clone()])),
+        }),
+        ..input_settings
+    }
+}
+
+/// Picks the formatters depending on whether `latexindent` is available (texlab's built-in
+/// LaTeX formatter is not implemented, so LaTeX documents are otherwise not formatted rather
+/// than failing), and uses the project's `latexindent` local settings file if any, unless the
+/// user has set the corresponding settings.
+///
+/// BibTeX is always formatted with texlab's built-in formatter by default, since `latexindent`
+/// has no special support for it.
+fn add_latexindent_defaults(
+    tools: &DetectedTools,
+    input_settings: TexlabSettings,
+) -> TexlabSettings {
+    let latexindent = match (input_settings.latexindent, &tools.latexindent_local) {
+        (latexindent, Some(local)) if tools.latexindent => {
+            let latexindent = latexindent.unwrap_or_default();
+            Some(TexlabLatexindentSettings {
+                local: latexindent.local.or_else(|| Some(local.clone())),
+                ..latexindent
+            })
+        }
+        (latexindent, _) => latexindent,
+    };
+    TexlabSettings {
+        latex_formatter: input_settings
+            .latex_formatter
+            .or(Some(if tools.latexindent {
+                TexlabFormatter::Latexindent
+            } else {
+                TexlabFormatter::None
+            })),
+        bibtex_formatter: input_settings
+            .bibtex_formatter
+            .or(Some(TexlabFormatter::Texlab)),
+        latexindent,
+        ..input_settings
+    }
+}
+
+/// Adds the ignored patterns of the chosen diagnostics presets to the user-provided ones.
+///
+/// Returns the resulting settings along with a description of each unknown preset skipped.
+fn add_diagnostics_presets(
+    options: &ExtensionOptions,
+    input_settings: TexlabSettings,
+) -> (TexlabSettings, Vec<String>) {
+    if options.diagnostics_presets.is_empty() {
+        return (input_settings, Vec::new());
+    }
+    let diagnostics = input_settings.diagnostics.unwrap_or_default();
+    let (ignored_patterns, unknown_presets) = diagnostics_presets::ignored_patterns(
+        &options.diagnostics_presets,
+        diagnostics.ignored_patterns.unwrap_or_default(),
+    );
+    (
         TexlabSettings {
-            build:
-                Some(TexlabBuildSettings {
-                    executable: Some(_),
-                    ..
-                }),
-            ..
-        } => input_settings,
-        _ => TexlabSettings {
-            build: Some(TexlabBuildSettings {
-                executable: Some("latexmk".to_string()),
-                args: Some(vec![
-                    "-e".into(),
-                    "$pdf_mode = 1 unless $pdf_mode != 0; if ($ARGV[-1] =~ /\\.log$/ or $ARGV[-1] =~ /latexmkrc$/) { exit 0; };".into(),
-                    "-interaction=nonstopmode".into(),
-                    "-synctex=1".into(),
-                    "%f".into(),
-                ]),
-                ..input_settings.build.unwrap_or_default()
+            diagnostics: Some(TexlabDiagnosticsSettings {
+                ignored_patterns: Some(ignored_patterns),
+                ..diagnostics
             }),
             ..input_settings
         },
-    }
+        unknown_presets,
+    )
 }
 
 /// Adds glyph preview in hover of symbol commands (used to be texlab default)
@@ -120,3 +768,902 @@ fn add_hover_default(input_settings: TexlabSettings) -> TexlabSettings {
         },
     }
 }
+
+/// Fills in `formatterLineLength` and the `latexindent` local settings file from the editor's
+/// formatting preferences, unless provided by the user.
+fn add_formatter_defaults(
+    formatting: &FormattingPreferences,
+    input_settings: TexlabSettings,
+) -> TexlabSettings {
+    let latexindent = match (input_settings.latexindent, &formatting.latexindent_local) {
+        (latexindent, None) => latexindent,
+        (latexindent, Some(local)) => {
+            let latexindent = latexindent.unwrap_or_default();
+            Some(TexlabLatexindentSettings {
+                local: latexindent.local.or_else(|| Some(local.clone())),
+                ..latexindent
+            })
+        }
+    };
+    TexlabSettings {
+        formatter_line_length: input_settings
+            .formatter_line_length
+            .or(formatting.line_length),
+        latexindent,
+        ..input_settings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extension_options::UnicodeEngine;
+    use crate::project_config::Engine;
+    use latexmkrc::Latexmkrc;
+    use tool_detection::TectonicOutput;
+    use types::TexlabForwardSearchSettings;
+
+    #[test]
+    fn test_partial_forward_search_is_merged_with_preset() {
+        let settings = TexlabSettings {
+            forward_search: Some(Some(TexlabForwardSearchSettings {
+                executable: Some("my-zathura-wrapper".to_string()),
+                args: None,
+            })),
+            ..Default::default()
+        };
+        let preset = Preview::Zathura.create_preset(&CommandName::Zed);
+
+        let settings = add_preview(
+            &Preview::Zathura,
+            &CommandName::Zed,
+            &Autoconfig::default(),
+            settings,
+        );
+
+        let forward_search = settings.forward_search.unwrap().unwrap();
+        assert_eq!(
+            forward_search.executable,
+            Some("my-zathura-wrapper".to_string())
+        );
+        assert_eq!(forward_search.args, preset.args);
+        assert_eq!(settings.build.unwrap().on_save, Some(true));
+    }
+
+    #[test]
+    fn test_null_forward_search_disables_autoconfiguration() {
+        let settings = TexlabSettings {
+            forward_search: Some(None),
+            ..Default::default()
+        };
+
+        let settings = add_preview(
+            &Preview::Zathura,
+            &CommandName::Zed,
+            &Autoconfig::default(),
+            settings,
+        );
+
+        assert!(matches!(settings.forward_search, Some(None)));
+        assert!(settings.build.is_none());
+    }
+
+    #[test]
+    fn test_describe_attributes_values_to_their_origin() {
+        let description = describe(
+            &Context {
+                previewer: &Some(Preview::Zathura),
+                zed_command: &CommandName::Zed,
+                options: &ExtensionOptions::default(),
+                formatting: &FormattingPreferences::default(),
+                project: &ProjectConfig::default(),
+                document: &RootDocument::default(),
+                tools: &DetectedTools::default(),
+            },
+            json!({ "texlab": { "build": { "onSave": false } } }),
+        )
+        .unwrap();
+
+        assert!(description.contains("texlab.build.onSave = false  [user]"));
+        assert!(description.contains("texlab.build.executable = \"latexmk\"  [extension default]"));
+        assert!(description.contains(
+            "texlab.forwardSearch.executable = \"zathura\"  [detected previewer (Zathura) and Zed command]"
+        ));
+    }
+
+    #[test]
+    fn test_unknown_keys_are_reported_and_ignored() {
+        let description = describe(
+            &Context {
+                previewer: &None,
+                zed_command: &CommandName::Zed,
+                options: &ExtensionOptions::default(),
+                formatting: &FormattingPreferences::default(),
+                project: &ProjectConfig::default(),
+                document: &RootDocument::default(),
+                tools: &DetectedTools::default(),
+            },
+            json!({ "texlab": { "build": { "onsave": true, "onSave": false } } }),
+        )
+        .unwrap();
+
+        assert!(description.contains("texlab.build.onSave = false  [user]"));
+        assert!(description.contains(
+            "`texlab.build.onsave`: unknown setting (did you mean `onSave`?), it was ignored"
+        ));
+    }
+
+    #[test]
+    fn test_autoconfig_can_be_switched_off() {
+        let options = ExtensionOptions {
+            autoconfig: Autoconfig {
+                build: false,
+                on_save: false,
+                forward_search: false,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let settings = get(
+            &Context {
+                previewer: &Some(Preview::Zathura),
+                zed_command: &CommandName::Zed,
+                options: &options,
+                formatting: &FormattingPreferences::default(),
+                project: &ProjectConfig::default(),
+                document: &RootDocument::default(),
+                tools: &DetectedTools::default(),
+            },
+            Value::Null,
+        )
+        .unwrap()
+        .0
+        .texlab
+        .unwrap();
+
+        assert!(settings.build.is_none());
+        assert!(settings.forward_search.is_none());
+        assert!(settings.hover.is_some());
+    }
+
+    #[test]
+    fn test_formatter_defaults_do_not_override_user_settings() {
+        let formatting = FormattingPreferences {
+            line_length: Some(100),
+            indent: None,
+            latexindent_local: Some("/work/latexindent-zed.yaml".to_string()),
+        };
+
+        let settings = add_formatter_defaults(&formatting, TexlabSettings::default());
+        assert_eq!(settings.formatter_line_length, Some(100));
+        assert_eq!(
+            settings.latexindent.unwrap().local.as_deref(),
+            Some("/work/latexindent-zed.yaml")
+        );
+
+        let settings = add_formatter_defaults(
+            &formatting,
+            TexlabSettings {
+                formatter_line_length: Some(72),
+                latexindent: Some(TexlabLatexindentSettings {
+                    local: Some("mine.yaml".to_string()),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        );
+        assert_eq!(settings.formatter_line_length, Some(72));
+        assert_eq!(
+            settings.latexindent.unwrap().local.as_deref(),
+            Some("mine.yaml")
+        );
+    }
+
+    /// The build settings sent to texlab for `document`, with the project's directories.
+    fn build_settings(
+        project: &ProjectConfig,
+        document: &RootDocument,
+        options: &ExtensionOptions,
+        tools: &DetectedTools,
+    ) -> TexlabBuildSettings {
+        add_project_directories(
+            project,
+            tools,
+            add_build_default(project, document, options, tools, TexlabSettings::default()),
+        )
+        .build
+        .unwrap()
+    }
+
+    /// Whether `args` contain `sequence` as consecutive arguments.
+    fn contains_sequence(args: &[String], sequence: &[&str]) -> bool {
+        args.windows(sequence.len())
+            .any(|window| window == sequence)
+    }
+
+    #[test]
+    fn test_build_default_follows_project_config() {
+        let project = ProjectConfig {
+            root: Some("/project/thesis/main.tex".to_string()),
+            engine: Some(Engine::Lualatex),
+            output_directory: Some("build".to_string()),
+            ..Default::default()
+        };
+
+        let build = build_settings(
+            &project,
+            &RootDocument::default(),
+            &ExtensionOptions::default(),
+            &DetectedTools::default(),
+        );
+        let args = build.args.unwrap();
+        assert!(contains_sequence(&args, &["-pdflua", "-outdir=build"]));
+        assert_eq!(args.last().unwrap(), "/project/thesis/main.tex");
+        assert_eq!(build.pdf_directory.as_deref(), Some("build"));
+        assert_eq!(build.aux_directory.as_deref(), Some("build"));
+    }
+
+    #[test]
+    fn test_build_default_uses_active_profile() {
+        let project = ProjectConfig {
+            profile: Some("final".to_string()),
+            ..Default::default()
+        };
+        let options = ExtensionOptions {
+            build_profile: Some("draft".to_string()),
+            ..Default::default()
+        };
+
+        let args = build_settings(
+            &project,
+            &RootDocument::default(),
+            &options,
+            &DetectedTools::default(),
+        )
+        .args
+        .unwrap();
+        assert!(contains_sequence(&args, &["-bibtex", "-g"]));
+        assert!(!args.iter().any(|arg| arg.contains("draft")));
+        assert_eq!(args.last().unwrap(), "%f");
+
+        // An unknown profile is reported, and the build goes on without it.
+        let options = ExtensionOptions {
+            build_profile: Some("nonexistent".to_string()),
+            ..Default::default()
+        };
+        let context = Context {
+            previewer: &None,
+            zed_command: &CommandName::Zed,
+            options: &options,
+            formatting: &FormattingPreferences::default(),
+            project: &ProjectConfig::default(),
+            document: &RootDocument::default(),
+            tools: &DetectedTools::default(),
+        };
+        let (settings, notes) = get(&context, Value::Null).unwrap();
+        assert_eq!(
+            notes,
+            ["unknown build profile \"nonexistent\", expected one of \"draft\", \"final\", it was ignored"]
+        );
+        let args = settings.texlab.unwrap().build.unwrap().args.unwrap();
+        assert!(contains_sequence(
+            &args,
+            &["-interaction=nonstopmode", "-synctex=1", "-e"]
+        ));
+    }
+
+    #[test]
+    fn test_build_default_follows_magic_comment() {
+        let document = RootDocument {
+            program: Some(Engine::Xelatex),
+            ..Default::default()
+        };
+        let args = |project: &ProjectConfig| {
+            build_settings(
+                project,
+                &document,
+                &ExtensionOptions::default(),
+                &DetectedTools::default(),
+            )
+            .args
+            .unwrap()
+        };
+
+        // The root document's engine is the fallback of the engine chosen at build time.
+        let selects_engine = |args: &[String]| {
+            args.iter().any(|arg| {
+                arg.contains(&root_document::select_engine(
+                    UnicodeEngine::Lualatex.engine(),
+                ))
+            })
+        };
+        let detected = args(&ProjectConfig::default());
+        assert!(detected.contains(&"-pdfxe".to_string()));
+        assert!(selects_engine(&detected));
+        let configured = args(&ProjectConfig {
+            engine: Some(Engine::Lualatex),
+            ..Default::default()
+        });
+        assert!(configured.contains(&"-pdflua".to_string()));
+        assert!(!configured.contains(&"-pdfxe".to_string()));
+        assert!(!selects_engine(&configured));
+    }
+
+    #[test]
+    fn test_build_default_uses_unicode_engine_when_needed() {
+        let document = RootDocument {
+            needs_unicode_engine: true,
+            ..Default::default()
+        };
+        let engine_flags = |document: &RootDocument, options: &ExtensionOptions| {
+            build_settings(
+                &ProjectConfig::default(),
+                document,
+                options,
+                &DetectedTools::default(),
+            )
+            .args
+            .unwrap()
+            .into_iter()
+            .filter(|arg| arg.starts_with("-pdf"))
+            .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            engine_flags(&document, &ExtensionOptions::default()),
+            ["-pdflua"]
+        );
+        let options = ExtensionOptions {
+            unicode_engine: UnicodeEngine::Xelatex,
+            ..Default::default()
+        };
+        assert_eq!(engine_flags(&document, &options), ["-pdfxe"]);
+        assert_eq!(
+            engine_flags(
+                &RootDocument {
+                    program: Some(Engine::Pdflatex),
+                    ..document
+                },
+                &options
+            ),
+            ["-pdf"]
+        );
+        assert!(engine_flags(&RootDocument::default(), &options).is_empty());
+    }
+
+    #[test]
+    fn test_build_default_falls_back_to_detected_root() {
+        let document = RootDocument {
+            path: Some("/project/main.tex".to_string()),
+            ..Default::default()
+        };
+        let args = build_settings(
+            &ProjectConfig::default(),
+            &document,
+            &ExtensionOptions::default(),
+            &DetectedTools::default(),
+        )
+        .args
+        .unwrap();
+        assert!(args
+            .iter()
+            .any(|arg| arg.contains("tex\\s+root")
+                && arg.contains("$ARGV[-1] = '/project/main.tex'; }")));
+        assert_eq!(args.last().unwrap(), "%f");
+    }
+
+    #[test]
+    fn test_build_default_builds_docstrip_sources() {
+        let has_docstrip_snippet = |args: &[String]| {
+            args.iter()
+                .any(|arg| arg.contains(docstrip::LATEXMK_SNIPPET))
+        };
+        let project = ProjectConfig {
+            root: Some("/project/mypkg.dtx".to_string()),
+            output_directory: Some("build".to_string()),
+            ..Default::default()
+        };
+
+        let dtx = build_settings(
+            &project,
+            &RootDocument::default(),
+            &ExtensionOptions::default(),
+            &DetectedTools::default(),
+        );
+        let args = dtx.args.unwrap();
+        assert!(has_docstrip_snippet(&args));
+        assert_eq!(args.last().unwrap(), "/project/mypkg.dtx");
+        assert_eq!(dtx.pdf_directory.as_deref(), Some("build"));
+        assert_eq!(dtx.filename.as_deref(), Some("mypkg.pdf"));
+
+        let sources = DetectedTools {
+            docstrip_sources: true,
+            ..Default::default()
+        };
+        let saved = build_settings(
+            &ProjectConfig::default(),
+            &RootDocument::default(),
+            &ExtensionOptions::default(),
+            &sources,
+        );
+        let args = saved.args.unwrap();
+        assert!(has_docstrip_snippet(&args));
+        assert_eq!(args.last().unwrap(), "%f");
+        assert_eq!(saved.filename, None);
+
+        for tools in [DetectedTools::default(), sources] {
+            let tex = build_settings(
+                &ProjectConfig {
+                    root: Some("/project/main.tex".to_string()),
+                    ..Default::default()
+                },
+                &RootDocument::default(),
+                &ExtensionOptions::default(),
+                &tools,
+            );
+            assert!(!tex.args.unwrap().iter().any(|arg| arg.contains("gind.ist")));
+            assert_eq!(tex.filename, None);
+        }
+        let args = build_settings(
+            &ProjectConfig::default(),
+            &RootDocument::default(),
+            &ExtensionOptions::default(),
+            &DetectedTools::default(),
+        )
+        .args
+        .unwrap();
+        assert!(!args.iter().any(|arg| arg.contains("gind.ist")));
+
+        // The `.dtx` branch must see the detected root, not the saved `.tex` file.
+        let detected = RootDocument {
+            path: Some("/project/mypkg.dtx".to_string()),
+            ..Default::default()
+        };
+        let args = build_settings(
+            &ProjectConfig::default(),
+            &detected,
+            &ExtensionOptions::default(),
+            &DetectedTools::default(),
+        )
+        .args
+        .unwrap();
+        let snippet = args.iter().find(|arg| arg.contains("gind.ist")).unwrap();
+        assert!(
+            snippet.find("$ARGV[-1] = '/project/mypkg.dtx'").unwrap()
+                < snippet.find(docstrip::LATEXMK_SNIPPET).unwrap()
+        );
+        assert_eq!(args.last().unwrap(), "%f");
+    }
+
+    #[test]
+    fn test_build_default_weaves_rnw_documents() {
+        let has_knitr_snippet =
+            |args: &[String]| args.iter().any(|arg| arg.contains(knitr::LATEXMK_SNIPPET));
+        let project = ProjectConfig {
+            root: Some("/project/report.Rnw".to_string()),
+            output_directory: Some("build".to_string()),
+            ..Default::default()
+        };
+
+        let rnw = build_settings(
+            &project,
+            &RootDocument::default(),
+            &ExtensionOptions::default(),
+            &DetectedTools::default(),
+        );
+        let args = rnw.args.unwrap();
+        assert!(has_knitr_snippet(&args));
+        assert_eq!(args.last().unwrap(), "/project/report.Rnw");
+        assert_eq!(rnw.pdf_directory.as_deref(), Some("build"));
+        assert_eq!(rnw.filename.as_deref(), Some("report.pdf"));
+
+        // Any saved `.Rnw` file is built, so every build is ready to weave it.
+        let saved = build_settings(
+            &ProjectConfig::default(),
+            &RootDocument::default(),
+            &ExtensionOptions::default(),
+            &DetectedTools::default(),
+        );
+        let args = saved.args.unwrap();
+        assert!(has_knitr_snippet(&args));
+        assert_eq!(args.last().unwrap(), "%f");
+        assert_eq!(saved.filename, None);
+    }
+
+    #[test]
+    fn test_build_default_snippets_survive_texlab_placeholder_substitution() {
+        let document = RootDocument {
+            path: Some("/project/main.tex".to_string()),
+            latexmk_rules: Some("/work/latexmkrc-zed-0123456789abcdef.pl".to_string()),
+            ..Default::default()
+        };
+        let tools = DetectedTools {
+            docstrip_sources: true,
+            ..Default::default()
+        };
+        let args = build_settings(
+            &ProjectConfig::default(),
+            &document,
+            &ExtensionOptions::default(),
+            &tools,
+        )
+        .args
+        .unwrap();
+        assert!(args.iter().any(|arg| arg.contains("gind.ist")));
+        assert!(args.iter().any(|arg| arg.contains("knitr::knit")));
+        assert!(args.iter().any(|arg| arg.contains("tex\\s+root")));
+        let (placeholder, snippets) = args.split_last().unwrap();
+        assert_eq!(placeholder, "%f");
+        assert!(!snippets.iter().any(|arg| arg.contains("%f")));
+    }
+
+    #[test]
+    fn test_build_default_uses_tectonic_when_latexmk_is_missing() {
+        let build = |project: &ProjectConfig, tools: &DetectedTools| {
+            build_settings(
+                project,
+                &RootDocument::default(),
+                &ExtensionOptions::default(),
+                tools,
+            )
+        };
+        let tools = DetectedTools {
+            tectonic: true,
+            ..Default::default()
+        };
+        let project = ProjectConfig {
+            output_directory: Some("out".to_string()),
+            ..Default::default()
+        };
+
+        let tectonic = build(&project, &tools);
+        assert_eq!(tectonic.executable.as_deref(), Some("tectonic"));
+        assert_eq!(
+            tectonic.args.unwrap(),
+            [
+                "-X",
+                "compile",
+                "--synctex",
+                "--keep-logs",
+                "--keep-intermediates",
+                "--outdir",
+                "out",
+                "%f"
+            ]
+        );
+        assert_eq!(tectonic.pdf_directory.as_deref(), Some("out"));
+
+        let latexmk = DetectedTools {
+            latexmk: true,
+            ..tools
+        };
+        assert_eq!(
+            build(&project, &latexmk).executable.as_deref(),
+            Some("latexmk")
+        );
+        let chosen = ProjectConfig {
+            backend: Some(BuildBackend::Tectonic),
+            ..Default::default()
+        };
+        assert_eq!(
+            build(&chosen, &latexmk).executable.as_deref(),
+            Some("tectonic")
+        );
+
+        let tectonic_project = DetectedTools {
+            tectonic_output: Some(TectonicOutput {
+                directory: "/project/build/thesis".to_string(),
+                name: "thesis".to_string(),
+            }),
+            ..latexmk
+        };
+        let tectonic = build(&ProjectConfig::default(), &tectonic_project);
+        assert_eq!(tectonic.args.unwrap(), ["-X", "build", "--keep-logs"]);
+        assert_eq!(
+            tectonic.pdf_directory.as_deref(),
+            Some("/project/build/thesis")
+        );
+        assert_eq!(tectonic.filename.as_deref(), Some("thesis.pdf"));
+    }
+
+    #[test]
+    fn test_build_default_follows_arara_directives() {
+        let document = RootDocument {
+            path: Some("/project/main.tex".to_string()),
+            arara: true,
+            ..Default::default()
+        };
+        let tools = DetectedTools {
+            latexmk: true,
+            arara: true,
+            ..Default::default()
+        };
+        let arara = build_settings(
+            &ProjectConfig::default(),
+            &document,
+            &ExtensionOptions::default(),
+            &tools,
+        );
+        assert_eq!(arara.executable.as_deref(), Some("arara"));
+        assert_eq!(arara.args.unwrap(), ["/project/main.tex"]);
+
+        let without_arara = DetectedTools {
+            arara: false,
+            ..tools
+        };
+        let latexmk = build_settings(
+            &ProjectConfig::default(),
+            &document,
+            &ExtensionOptions::default(),
+            &without_arara,
+        );
+        assert_eq!(latexmk.executable.as_deref(), Some("latexmk"));
+
+        let hint = |document: &RootDocument, tools: &DetectedTools| {
+            arara_synctex_hint(&Context {
+                previewer: &None,
+                zed_command: &CommandName::Zed,
+                options: &ExtensionOptions::default(),
+                formatting: &FormattingPreferences::default(),
+                project: &ProjectConfig::default(),
+                document,
+                tools,
+            })
+        };
+        let arara = DetectedTools {
+            arara: true,
+            ..Default::default()
+        };
+        assert!(hint(&document, &arara).unwrap().contains("`synctex: yes`"));
+        assert!(hint(&document, &without_arara).is_none());
+        let with_synctex = RootDocument {
+            arara_synctex: true,
+            ..document
+        };
+        assert!(hint(&with_synctex, &arara).is_none());
+    }
+
+    #[test]
+    fn test_build_default_runs_makefile_target() {
+        let tools = DetectedTools {
+            latexmk: true,
+            makefile: Some(BuildFile {
+                path: "/project/Makefile".to_string(),
+                targets: vec!["pdf".to_string(), "clean".to_string()],
+            }),
+            ..Default::default()
+        };
+        let project = ProjectConfig {
+            pdf: Some("/project/build/thesis.pdf".to_string()),
+            ..Default::default()
+        };
+        let build = |options: &ExtensionOptions| {
+            build_settings(&project, &RootDocument::default(), options, &tools)
+        };
+
+        let make = build(&ExtensionOptions::default());
+        assert_eq!(make.executable.as_deref(), Some("make"));
+        assert_eq!(make.args.unwrap(), ["-C", "/project", "pdf"]);
+        assert_eq!(make.pdf_directory.as_deref(), Some("/project/build"));
+        assert_eq!(make.filename.as_deref(), Some("thesis.pdf"));
+
+        let options = ExtensionOptions {
+            build_target: Some("document".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(build(&options).executable.as_deref(), Some("latexmk"));
+    }
+
+    #[test]
+    fn test_build_default_respects_latexmkrc() {
+        let tools = DetectedTools {
+            latexmkrc: Some(Latexmkrc {
+                pdf_mode: Some(5),
+                out_dir: Some("build".to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let document = RootDocument {
+            needs_unicode_engine: true,
+            ..Default::default()
+        };
+        let build = |project: &ProjectConfig| {
+            build_settings(project, &document, &ExtensionOptions::default(), &tools)
+        };
+
+        let build_with_latexmkrc = build(&ProjectConfig::default());
+        let args = build_with_latexmkrc.args.unwrap();
+        assert!(!args.iter().any(|arg| arg.contains("$pdf_mode")));
+        assert!(!args
+            .iter()
+            .any(|arg| arg.starts_with("-pdf") || arg.starts_with("-outdir")));
+        assert_eq!(build_with_latexmkrc.pdf_directory.as_deref(), Some("build"));
+        assert_eq!(build_with_latexmkrc.aux_directory.as_deref(), Some("build"));
+        assert_eq!(build_with_latexmkrc.log_directory.as_deref(), Some("build"));
+
+        let configured = build(&ProjectConfig {
+            engine: Some(Engine::Xelatex),
+            output_directory: Some("out".to_string()),
+            ..Default::default()
+        });
+        let args = configured.args.unwrap();
+        assert!(args.contains(&"-pdfxe".to_string()));
+        assert!(args.contains(&"-outdir=out".to_string()));
+        assert_eq!(configured.pdf_directory.as_deref(), Some("out"));
+    }
+
+    #[test]
+    fn test_shell_escape_is_opt_in() {
+        let minted = RootDocument {
+            shell_escape_packages: vec!["minted".to_string()],
+            ..Default::default()
+        };
+        let args = |project: &ProjectConfig, options: &ExtensionOptions| {
+            build_settings(project, &minted, options, &DetectedTools::default())
+                .args
+                .unwrap()
+        };
+        let hint =
+            |project: &ProjectConfig, options: &ExtensionOptions, document: &RootDocument| {
+                shell_escape_hint(&Context {
+                    previewer: &None,
+                    zed_command: &CommandName::Zed,
+                    options,
+                    formatting: &FormattingPreferences::default(),
+                    project,
+                    document,
+                    tools: &DetectedTools::default(),
+                })
+            };
+
+        let project = ProjectConfig::default();
+        let options = ExtensionOptions::default();
+        assert!(!args(&project, &options)
+            .iter()
+            .any(|arg| arg.starts_with("-shell")));
+        let minted_hint = hint(&project, &options, &minted).unwrap();
+        assert!(minted_hint.contains("need shell escape (minted)"));
+        assert!(minted_hint.contains("option to \"full\""));
+        let pythontex = RootDocument {
+            shell_escape_packages: vec!["pythontex".to_string()],
+            ..Default::default()
+        };
+        assert!(hint(&project, &options, &pythontex)
+            .unwrap()
+            .contains("option to \"restricted\""));
+
+        // Shell escape is only enabled by the extension option.
+        let untrusted = ProjectConfig {
+            ignored_shell_escape: Some("full".to_string()),
+            ..Default::default()
+        };
+        assert!(!args(&untrusted, &options)
+            .iter()
+            .any(|arg| arg.starts_with("-shell")));
+        assert!(hint(&untrusted, &options, &minted)
+            .unwrap()
+            .contains("is ignored"));
+
+        let options = ExtensionOptions {
+            shell_escape: Some(ShellEscape::Restricted),
+            ..Default::default()
+        };
+        assert!(args(&project, &options).contains(&"-shell-restricted".to_string()));
+        assert!(hint(&project, &options, &minted).is_none());
+    }
+
+    #[test]
+    fn test_build_default_loads_generated_latexmk_rules() {
+        let document = RootDocument {
+            latexmk_rules: Some("/work/latexmkrc-zed-0123456789abcdef.pl".to_string()),
+            ..Default::default()
+        };
+        let args = build_settings(
+            &ProjectConfig::default(),
+            &document,
+            &ExtensionOptions::default(),
+            &DetectedTools::default(),
+        )
+        .args
+        .unwrap();
+        assert!(contains_sequence(
+            &args,
+            &["-r", "/work/latexmkrc-zed-0123456789abcdef.pl"]
+        ));
+        assert!(args.contains(&"-synctex=1".to_string()));
+    }
+
+    #[test]
+    fn test_chktex_enabled_when_configured_for_project() {
+        let tools = DetectedTools {
+            chktexrc: Some("/project/.chktexrc".to_string()),
+            ..Default::default()
+        };
+
+        let chktex = add_chktex_defaults(&tools, TexlabSettings::default())
+            .chktex
+            .unwrap();
+        assert_eq!(chktex.on_open_and_save, Some(true));
+        assert_eq!(chktex.on_edit, Some(false));
+        assert_eq!(
+            chktex.additional_args.unwrap(),
+            ["-l", "/project/.chktexrc"]
+        );
+
+        let chktex = add_chktex_defaults(
+            &tools,
+            TexlabSettings {
+                chktex: Some(TexlabChktexSettings {
+                    on_open_and_save: Some(false),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        )
+        .chktex
+        .unwrap();
+        assert_eq!(chktex.on_open_and_save, Some(false));
+
+        assert!(
+            add_chktex_defaults(&DetectedTools::default(), TexlabSettings::default())
+                .chktex
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_formatters_depend_on_latexindent_availability() {
+        let settings =
+            add_latexindent_defaults(&DetectedTools::default(), TexlabSettings::default());
+        assert_eq!(settings.latex_formatter, Some(TexlabFormatter::None));
+        assert_eq!(settings.bibtex_formatter, Some(TexlabFormatter::Texlab));
+        assert!(settings.latexindent.is_none());
+
+        let tools = DetectedTools {
+            latexindent: true,
+            latexindent_local: Some("/project/localSettings.yaml".to_string()),
+            ..Default::default()
+        };
+        let settings = add_latexindent_defaults(&tools, TexlabSettings::default());
+        assert_eq!(settings.latex_formatter, Some(TexlabFormatter::Latexindent));
+        assert_eq!(
+            settings.latexindent.unwrap().local.as_deref(),
+            Some("/project/localSettings.yaml")
+        );
+
+        let settings = add_latexindent_defaults(
+            &tools,
+            TexlabSettings {
+                latex_formatter: Some(TexlabFormatter::None),
+                ..Default::default()
+            },
+        );
+        assert_eq!(settings.latex_formatter, Some(TexlabFormatter::None));
+    }
+
+    #[test]
+    fn test_diagnostics_presets_are_added_to_user_patterns() {
+        let options = ExtensionOptions {
+            diagnostics_presets: vec!["quiet-boxes".to_string()],
+            ..Default::default()
+        };
+        let settings = add_diagnostics_presets(
+            &options,
+            TexlabSettings {
+                diagnostics: Some(TexlabDiagnosticsSettings {
+                    allowed_patterns: Some(vec!["Overfull".to_string()]),
+                    ignored_patterns: Some(vec!["Citation".to_string()]),
+                }),
+                ..Default::default()
+            },
+        )
+        .0;
+        let diagnostics = settings.diagnostics.unwrap();
+        assert_eq!(diagnostics.allowed_patterns.unwrap(), ["Overfull"]);
+        assert_eq!(
+            diagnostics.ignored_patterns.unwrap(),
+            ["Citation", r"(Overfull|Underfull) \\[hv]box"]
+        );
+    }
+}
@@ -0,0 +1,136 @@
+//! Tracks where each value of the effective texlab settings came from, so that a single
+//! dump of the configuration can explain why texlab behaves the way it does.
+//!
+//! The settings are snapshotted (as JSON) after each stage of [`super::resolve`], and each
+//! value in the final settings is attributed to the last stage which set or changed it.
+
+use crate::zed_command::CommandName;
+use std::collections::BTreeMap;
+use zed_extension_api::serde_json::Value;
+
+/// Where a value in the effective texlab settings came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Origin {
+    /// Provided in `lsp.texlab.settings`.
+    User,
+    /// Filled in by the extension, e.g. the default `latexmk` build command.
+    ExtensionDefault,
+    /// Provided in the project configuration file (`.zed-latex.toml`).
+    ProjectFile,
+    /// Derived from the editor settings (Zed language settings and `.editorconfig`).
+    EditorSettings,
+    /// Derived from the tools (and their configuration files) detected in the worktree.
+    DetectedTools,
+    /// Derived from the detected PDF previewer and Zed command.
+    Detected,
+}
+
+/// Renders the final settings of `stages` as one line per value, annotated with its origin.
+///
+/// `stages` are the settings (serialized as JSON) after each stage, in order.
+pub fn describe(
+    previewer_name: Option<&str>,
+    zed_command: &CommandName,
+    stages: &[(Origin, Value)],
+) -> String {
+    let mut lines = vec![
+        format!(
+            "Detected previewer: {}",
+            previewer_name.unwrap_or("none (or language server not started yet)")
+        ),
+        format!("Zed command: {}", zed_command.to_str()),
+        String::new(),
+    ];
+
+    let mut previous = BTreeMap::new();
+    let mut origins = BTreeMap::new();
+    for (origin, settings) in stages {
+        let mut current = BTreeMap::new();
+        flatten(settings, String::new(), &mut current);
+        for (path, value) in &current {
+            if previous.get(path) != Some(value) {
+                origins.insert(path.clone(), *origin);
+            }
+        }
+        previous = current;
+    }
+
+    for (path, value) in &previous {
+        let origin = match origins.get(path) {
+            Some(Origin::User) | None => "user".to_string(),
+            Some(Origin::ExtensionDefault) => "extension default".to_string(),
+            Some(Origin::ProjectFile) => "project file".to_string(),
+            Some(Origin::EditorSettings) => "editor settings".to_string(),
+            Some(Origin::DetectedTools) => "detected tools".to_string(),
+            Some(Origin::Detected) => format!(
+                "detected previewer ({}) and Zed command",
+                previewer_name.unwrap_or("none")
+            ),
+        };
+        lines.push(format!("{path} = {value}  [{origin}]"));
+    }
+
+    lines.join("\n")
+}
+
+/// Collects the non-object values of `value`, keyed by their dotted JSON path.
+fn flatten(value: &Value, path: String, leaves: &mut BTreeMap<String, Value>) {
+    match value {
+        Value::Object(map) => {
+            for (key, field) in map {
+                let field_path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{path}.{key}")
+                };
+                flatten(field, field_path, leaves);
+            }
+        }
+        _ => {
+            leaves.insert(path, value.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zed_extension_api::serde_json::json;
+
+    #[test]
+    fn test_values_are_attributed_to_last_stage_changing_them() {
+        let stages = [
+            (
+                Origin::User,
+                json!({ "texlab": { "build": { "onSave": false } } }),
+            ),
+            (
+                Origin::ExtensionDefault,
+                json!({ "texlab": { "build": { "onSave": false, "executable": "latexmk" } } }),
+            ),
+            (
+                Origin::Detected,
+                json!({ "texlab": {
+                    "build": { "onSave": false, "executable": "latexmk", "forwardSearchAfter": true },
+                    "forwardSearch": { "executable": "zathura" }
+                } }),
+            ),
+        ];
+
+        let description = describe(Some("Zathura"), &CommandName::Zed, &stages);
+
+        assert_eq!(
+            description,
+            [
+                "Detected previewer: Zathura",
+                "Zed command: zed",
+                "",
+                "texlab.build.executable = \"latexmk\"  [extension default]",
+                "texlab.build.forwardSearchAfter = true  [detected previewer (Zathura) and Zed command]",
+                "texlab.build.onSave = false  [user]",
+                "texlab.forwardSearch.executable = \"zathura\"  [detected previewer (Zathura) and Zed command]",
+            ]
+            .join("\n")
+        );
+    }
+}
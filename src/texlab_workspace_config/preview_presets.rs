@@ -4,9 +4,10 @@
 //! The module primarily:
 //! - Defines supported PDF previewers
 //! - Creates appropriate `texlab.settings.forwardSearch` settings for each previewer
-//! - Detects an available previewer in the system
+//! - Detects an available previewer in the system (preferring the one chosen by the user, if any)
 
 use super::types::TexlabForwardSearchSettings;
+use crate::extension_options::PreviewerChoice;
 use crate::zed_command::CommandName;
 use chrono::TimeZone;
 use chrono::Utc;
@@ -35,6 +36,19 @@ pub enum Preview {
 }
 
 impl Preview {
+    /// Human-readable name of the previewer.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Preview::Zathura => "Zathura",
+            Preview::Skim => "Skim",
+            Preview::Sioyek => "Sioyek",
+            Preview::QPDFView => "qpdfview",
+            Preview::Okular => "Okular",
+            Preview::SumatraPDF { .. } => "SumatraPDF",
+            Preview::Evince { .. } => "Evince",
+        }
+    }
+
     /// Creates the appropriate `texlab.settings.forwardSearch` settings for the specific PDF previewer.
     ///
     /// This function configures the executable path and command line arguments needed for
@@ -47,7 +61,7 @@ impl Preview {
     /// # Returns
     ///
     /// `TexlabForwardSearchSettings` containing the executable and arguments for forward search
-    pub fn create_preset(&self, zed_command: CommandName) -> TexlabForwardSearchSettings {
+    pub fn create_preset(&self, zed_command: &CommandName) -> TexlabForwardSearchSettings {
         match self {
             Preview::Zathura => TexlabForwardSearchSettings {
                 executable: Some("zathura".to_string()),
@@ -142,110 +156,137 @@ impl Preview {
     /// # Arguments
     ///
     /// * `worktree` - Reference to the Zed worktree, used for checking executable availability
+    /// * `preferred` - Previewer chosen by the user (`previewer` extension option), checked
+    ///   before any other. Detection is skipped entirely if it is `PreviewerChoice::None`.
     ///
     /// # Returns
     ///
-    /// `Option<Preview>` containing the first supported PDF previewer found, or `None` if no
-    /// supported previewer is available
-    pub fn determine(worktree: &zed::Worktree) -> Option<Preview> {
-        let (platform, _) = zed::current_platform();
+    /// `Option<Preview>` containing the preferred PDF previewer if available, otherwise the
+    /// first supported previewer found, or `None` if no supported previewer is available
+    pub fn determine(
+        worktree: &zed::Worktree,
+        preferred: Option<PreviewerChoice>,
+    ) -> Option<Preview> {
+        const DETECTION_ORDER: [PreviewerChoice; 7] = [
+            PreviewerChoice::Skim,
+            PreviewerChoice::Sumatrapdf,
+            PreviewerChoice::Evince,
+            PreviewerChoice::Zathura,
+            PreviewerChoice::Sioyek,
+            PreviewerChoice::Qpdfview,
+            PreviewerChoice::Okular,
+        ];
 
-        if platform == zed::Os::Mac {
-            if worktree
-                .which("/Applications/Skim.app/Contents/SharedSupport/displayline")
-                .is_some()
-            {
-                return Some(Preview::Skim);
-            }
+        if preferred == Some(PreviewerChoice::None) {
+            return None;
         }
+        preferred
+            .into_iter()
+            .chain(DETECTION_ORDER)
+            .find_map(|choice| Self::detect(choice, worktree))
+    }
 
-        if platform == zed::Os::Windows {
-            let localappdata = worktree
-                .shell_env()
-                .iter()
-                .find(|&var| var.0 == "LOCALAPPDATA")?
-                .1
-                .clone();
-            let potential_sumatra_path = format!("{localappdata}\\SumatraPDF\\SumatraPDF.exe");
-            if worktree.which(&potential_sumatra_path).is_some() {
-                return Some(Preview::SumatraPDF {
-                    path: potential_sumatra_path,
-                });
+    /// Checks whether the given previewer is available on the system.
+    fn detect(choice: PreviewerChoice, worktree: &zed::Worktree) -> Option<Preview> {
+        let (platform, _) = zed::current_platform();
+
+        match choice {
+            PreviewerChoice::Skim => (platform == zed::Os::Mac
+                && worktree
+                    .which("/Applications/Skim.app/Contents/SharedSupport/displayline")
+                    .is_some())
+            .then_some(Preview::Skim),
+            PreviewerChoice::Sumatrapdf => {
+                if platform != zed::Os::Windows {
+                    return None;
+                }
+                let localappdata = worktree
+                    .shell_env()
+                    .iter()
+                    .find(|&var| var.0 == "LOCALAPPDATA")?
+                    .1
+                    .clone();
+                let potential_sumatra_path = format!("{localappdata}\\SumatraPDF\\SumatraPDF.exe");
+                worktree
+                    .which(&potential_sumatra_path)
+                    .is_some()
+                    .then_some(Preview::SumatraPDF {
+                        path: potential_sumatra_path,
+                    })
             }
+            PreviewerChoice::Evince => {
+                worktree.which("evince")?;
+                Self::acquire_evince_synctex()
+            }
+            PreviewerChoice::Zathura => worktree.which("zathura").map(|_| Preview::Zathura),
+            PreviewerChoice::Sioyek => worktree.which("sioyek").map(|_| Preview::Sioyek),
+            PreviewerChoice::Qpdfview => worktree.which("qpdfview").map(|_| Preview::QPDFView),
+            PreviewerChoice::Okular => worktree.which("okular").map(|_| Preview::Okular),
+            PreviewerChoice::None => None,
         }
+    }
 
-        if worktree.which("evince").is_some() {
-            const SCRIPT_NAME: &str = "evince_synctex.py";
-            const GITHUB_REPO_NAME: &str = "lnay/evince-synctex";
-            const COMMIT_HASH: &str = "635f7863408a44f3aaa0dbad512f2ba6ac1ad6ff";
-            // Following values refer to the estimated latest time when a
-            // release of this extension updates the version of
-            // evince_synctex.py is to be downloaded. (i.e. possibly the near
-            // future to account for Zed extension release pipeline).
-            const LAST_UPDATE_YEAR: i32 = 2025;
-            const LAST_UPDATE_MONTH: u32 = 3;
-            const LAST_UPDATE_DAY: u32 = 20;
+    /// Evince can only be used for forward (and inverse) search through the
+    /// `evince_synctex.py` script, which is downloaded to the extension work directory
+    /// if not already there (and up to date).
+    fn acquire_evince_synctex() -> Option<Preview> {
+        const SCRIPT_NAME: &str = "evince_synctex.py";
+        const GITHUB_REPO_NAME: &str = "lnay/evince-synctex";
+        const COMMIT_HASH: &str = "635f7863408a44f3aaa0dbad512f2ba6ac1ad6ff";
+        // Following values refer to the estimated latest time when a
+        // release of this extension updates the version of
+        // evince_synctex.py is to be downloaded. (i.e. possibly the near
+        // future to account for Zed extension release pipeline).
+        const LAST_UPDATE_YEAR: i32 = 2025;
+        const LAST_UPDATE_MONTH: u32 = 3;
+        const LAST_UPDATE_DAY: u32 = 20;
 
-            // The following would all be useless if the string path for
-            // evince_synctex.py in CWD cannot be obtained:
-            if let Some(evince_synctex_path) = (|| {
-                Some(format!(
-                    "{}/{SCRIPT_NAME}",
-                    std::env::current_dir().ok()?.as_os_str().to_str()?
-                ))
-            })() {
-                // Check if `evince_synctex.py` has already downloaded to
-                // latex extension work directory since the last time this
-                // extension updated the version of `evince_synctex.py`.
-                if let Ok(stat) = std::fs::metadata(SCRIPT_NAME) {
-                    if stat.is_file() {
-                        if let Ok(last_download) = stat.modified() {
-                            // SystemTime estimate for last extension update.
-                            // When evince_synctex.py was updated:
-                            let last_update: SystemTime = Utc
-                                .with_ymd_and_hms(
-                                    LAST_UPDATE_YEAR,
-                                    LAST_UPDATE_MONTH,
-                                    LAST_UPDATE_DAY,
-                                    0,
-                                    0,
-                                    0,
-                                )
-                                .single()
-                                .unwrap_or_default()
-                                .into();
-                            if last_download > last_update {
-                                return Some(Preview::Evince {
-                                    evince_synctex_path,
-                                });
-                            }
+        // The following would all be useless if the string path for
+        // evince_synctex.py in CWD cannot be obtained:
+        if let Some(evince_synctex_path) = (|| {
+            Some(format!(
+                "{}/{SCRIPT_NAME}",
+                std::env::current_dir().ok()?.as_os_str().to_str()?
+            ))
+        })() {
+            // Check if `evince_synctex.py` has already downloaded to
+            // latex extension work directory since the last time this
+            // extension updated the version of `evince_synctex.py`.
+            if let Ok(stat) = std::fs::metadata(SCRIPT_NAME) {
+                if stat.is_file() {
+                    if let Ok(last_download) = stat.modified() {
+                        // SystemTime estimate for last extension update.
+                        // When evince_synctex.py was updated:
+                        let last_update: SystemTime = Utc
+                            .with_ymd_and_hms(
+                                LAST_UPDATE_YEAR,
+                                LAST_UPDATE_MONTH,
+                                LAST_UPDATE_DAY,
+                                0,
+                                0,
+                                0,
+                            )
+                            .single()
+                            .unwrap_or_default()
+                            .into();
+                        if last_download > last_update {
+                            return Some(Preview::Evince {
+                                evince_synctex_path,
+                            });
                         }
                     }
                 }
-                // Choose evince for preview, provided that evince_synctex.py
-                // downloads successfully.
-                if zed::download_file(
-                    format!("https://raw.githubusercontent.com/{GITHUB_REPO_NAME}/{COMMIT_HASH}/{SCRIPT_NAME}").as_str(),
-                    SCRIPT_NAME,
-                    zed::DownloadedFileType::Uncompressed
-                ).is_ok() {
-                    return Some(Preview::Evince { evince_synctex_path });
-                }
+            }
+            // Choose evince for preview, provided that evince_synctex.py
+            // downloads successfully.
+            if zed::download_file(
+                format!("https://raw.githubusercontent.com/{GITHUB_REPO_NAME}/{COMMIT_HASH}/{SCRIPT_NAME}").as_str(),
+                SCRIPT_NAME,
+                zed::DownloadedFileType::Uncompressed
+            ).is_ok() {
+                return Some(Preview::Evince { evince_synctex_path });
             }
         }
-        if worktree.which("zathura").is_some() {
-            return Some(Preview::Zathura);
-        }
-        if worktree.which("sioyek").is_some() {
-            return Some(Preview::Sioyek);
-        }
-        if worktree.which("qpdfview").is_some() {
-            return Some(Preview::QPDFView);
-        }
-        if worktree.which("okular").is_some() {
-            return Some(Preview::Okular);
-        }
-
         None
     }
 }
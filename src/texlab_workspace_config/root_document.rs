@@ -0,0 +1,475 @@
+//! Detection of the root document of the worktree, and information read from it which
+//! affects how it is built.
+//!
+//! The root document is, in order of precedence:
+//! - `root` from the project configuration,
+//! - the first of the `@default_files` of the project's latexmkrc,
+//! - the first conventionally named document (e.g. `main.tex`) with a `\documentclass`.
+//!
+//! Only the magic comments at the top of the document (before any other content) are taken
+//! into account, as in TeXShop and TeXworks:
+//! ```latex
+//! % !TEX program = lualatex
+//! ```
+//! The preamble is scanned for the document class, for packages which only work with a
+//! Unicode engine (LuaLaTeX or XeLaTeX), such as `fontspec`, and for packages which run
+//! external tools (and so need shell escape), such as `minted`.
+//!
+//! Packages needing extra tool runs between passes get the corresponding `latexmk` rules
+//! (see [`latexmk_rules`]).
+//!
+//! `% arara:` directives anywhere in the document mean it is meant to be built with arara,
+//! which only enables SyncTeX if a directive asks for it (e.g. `synctex: yes`).
+//!
+//! `% !TEX root` magic comments are found in the file being built rather than in the root
+//! document, so they are followed by `latexmk` itself (see [`follow_root_magic_comment`]).
+//! The same goes for the `% !TEX program` magic comment and the Unicode engine packages of the
+//! document actually built (see [`select_engine`]), those of the root document only being a
+//! fallback.
+
+use super::latexmk_rules;
+use super::latexmkrc::Latexmkrc;
+use crate::project_config::{Engine, ProjectConfig};
+use zed_extension_api as zed;
+
+/// Packages which only work with a Unicode engine.
+const UNICODE_ENGINE_PACKAGES: &[&str] = &["fontspec", "unicode-math", "polyglossia"];
+
+/// Packages which run external tools while compiling (`pythontex` only in some modes), and so
+/// need shell escape.
+const SHELL_ESCAPE_PACKAGES: &[&str] = &["minted", "svg", "gnuplottex", "pythontex"];
+
+/// Conventional names of root documents, in order of preference.
+const CONVENTIONAL_NAMES: &[&str] = &["main.tex", "thesis.tex", "paper.tex", "document.tex"];
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct RootDocument {
+    /// Absolute path to the root document, if known.
+    pub path: Option<String>,
+    /// Engine requested by a `% !TEX program` (or TeXShop's `% !TEX TS-program`) magic comment.
+    pub program: Option<Engine>,
+    /// Whether the preamble loads a package which only works with a Unicode engine.
+    pub needs_unicode_engine: bool,
+    /// Class loaded by `\documentclass`.
+    pub class: Option<String>,
+    /// Packages loaded by the preamble.
+    pub packages: Vec<String>,
+    /// Absolute path to a generated latexmkrc with the rules running the tools needed by the
+    /// loaded packages between passes (e.g. `pythontex`), if any.
+    pub latexmk_rules: Option<String>,
+    /// Packages loaded by the preamble which need shell escape.
+    pub shell_escape_packages: Vec<String>,
+    /// Whether the document has `% arara:` directives.
+    pub arara: bool,
+    /// Whether one of the document's arara directives enables SyncTeX.
+    pub arara_synctex: bool,
+}
+
+impl RootDocument {
+    /// Determines the root document of the worktree (with the help of the project's
+    /// `latexmkrc`, if any) and reads it.
+    pub fn for_worktree(
+        worktree: &zed::Worktree,
+        project: &ProjectConfig,
+        latexmkrc: Option<&Latexmkrc>,
+    ) -> Self {
+        let root_path = format!("{}/", worktree.root_path());
+        let configured = project
+            .root
+            .as_deref()
+            .and_then(|root| root.strip_prefix(&root_path))
+            .map(str::to_string);
+        let detected = || {
+            latexmkrc
+                .and_then(|latexmkrc| latexmkrc.default_files.first().cloned())
+                .or_else(|| {
+                    CONVENTIONAL_NAMES
+                        .iter()
+                        .find(|name| {
+                            worktree
+                                .read_text_file(name)
+                                .is_ok_and(|contents| contents.contains("\\documentclass"))
+                        })
+                        .map(|name| name.to_string())
+                })
+        };
+        let Some(path) = configured.or_else(detected) else {
+            return Self::default();
+        };
+        let document = worktree
+            .read_text_file(&path)
+            .map(|contents| Self::parse(&contents))
+            .unwrap_or_default();
+        RootDocument {
+            path: Some(format!("{root_path}{path}")),
+            latexmk_rules: latexmk_rules::write(&worktree.root_path(), &document.packages),
+            ..document
+        }
+    }
+
+    fn parse(contents: &str) -> Self {
+        let mut document = RootDocument::default();
+        for (key, value) in magic_comments(contents) {
+            if key.eq_ignore_ascii_case("program") || key.eq_ignore_ascii_case("TS-program") {
+                document.program = document.program.or(Engine::from_program(value));
+            }
+        }
+        let preamble = preamble(contents);
+        document.class = loaded_names(&preamble, "\\documentclass")
+            .next()
+            .map(str::to_string);
+        document.packages = loaded_packages(&preamble).map(str::to_string).collect();
+        document.needs_unicode_engine = document
+            .packages
+            .iter()
+            .any(|package| UNICODE_ENGINE_PACKAGES.contains(&package.as_str()));
+        document.shell_escape_packages = document
+            .packages
+            .iter()
+            .filter(|package| SHELL_ESCAPE_PACKAGES.contains(&package.as_str()))
+            .cloned()
+            .collect();
+        let mut directives = contents.lines().filter_map(arara_directive).peekable();
+        document.arara = directives.peek().is_some();
+        document.arara_synctex = directives.any(enables_synctex);
+        document
+    }
+}
+
+/// The directive if `line` is an arara directive, e.g. `pdflatex: { synctex: yes }` for
+/// `% arara: pdflatex: { synctex: yes }`.
+fn arara_directive(line: &str) -> Option<&str> {
+    line.trim_start()
+        .strip_prefix('%')
+        .and_then(|comment| comment.trim_start().strip_prefix("arara"))
+        .and_then(|directive| directive.trim_start().strip_prefix(':'))
+}
+
+/// Whether an arara `directive` sets its `synctex` parameter to a true value.
+fn enables_synctex(directive: &str) -> bool {
+    directive.match_indices("synctex").any(|(index, key)| {
+        directive[index + key.len()..]
+            .trim_start_matches(['\'', '"'])
+            .trim_start()
+            .strip_prefix(':')
+            .is_some_and(|value| {
+                let value = value.trim_start().trim_start_matches(['\'', '"']);
+                ["yes", "true", "on"]
+                    .iter()
+                    .any(|enabled| value.starts_with(enabled))
+            })
+    })
+}
+
+/// The part of `contents` before `\begin{document}`, without comments.
+fn preamble(contents: &str) -> String {
+    contents
+        .lines()
+        .map(|line| {
+            let mut escaped = false;
+            let end = line
+                .char_indices()
+                .find(|&(_, c)| {
+                    let comment = c == '%' && !escaped;
+                    escaped = c == '\\' && !escaped;
+                    comment
+                })
+                .map_or(line.len(), |(index, _)| index);
+            &line[..end]
+        })
+        .take_while(|line| !line.contains("\\begin{document}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Names of the packages loaded with `\usepackage` or `\RequirePackage` in `preamble`.
+fn loaded_packages(preamble: &str) -> impl Iterator<Item = &str> {
+    loaded_names(preamble, "\\usepackage").chain(loaded_names(preamble, "\\RequirePackage"))
+}
+
+/// Names loaded by each `command` in `preamble`, e.g. `\usepackage[options]{name,other}`.
+fn loaded_names<'a>(preamble: &'a str, command: &'static str) -> impl Iterator<Item = &'a str> {
+    preamble
+        .split(command)
+        .skip(1)
+        .filter_map(|command| {
+            let command = command.trim_start();
+            let command = match command.strip_prefix('[') {
+                Some(options) => options.split_once(']')?.1.trim_start(),
+                None => command,
+            };
+            Some(command.strip_prefix('{')?.split_once('}')?.0)
+        })
+        .flat_map(|packages| packages.split(',').map(str::trim))
+}
+
+/// The `% !TEX key = value` magic comments (as `(key, value)`) at the top of `contents`.
+fn magic_comments(contents: &str) -> impl Iterator<Item = (&str, &str)> {
+    contents
+        .lines()
+        .map(str::trim)
+        .take_while(|line| line.is_empty() || line.starts_with('%'))
+        .filter_map(|line| {
+            let comment = line.trim_start_matches('%').trim_start();
+            let directive = comment.strip_prefix('!')?.trim_start();
+            let (tex, directive) = directive.split_at_checked(3)?;
+            if !tex.eq_ignore_ascii_case("tex") {
+                return None;
+            }
+            let (key, value) = directive.split_once('=')?;
+            Some((key.trim(), value.trim()))
+        })
+}
+
+/// Perl code for `latexmk -e`, replacing the document to build (the last argument) by the
+/// one named in its `% !TEX root` magic comment (relative to its directory), or else by
+/// `fallback` (if any) unless it is a standalone document (with its own `\documentclass`).
+pub fn follow_root_magic_comment(fallback: Option<&str>) -> String {
+    let fallback = match fallback {
+        Some(fallback) => format!(
+            " elsif (!$standalone and $ARGV[-1] =~ /\\.tex$/) {{ $ARGV[-1] = '{}'; }}",
+            fallback.replace('\\', "\\\\").replace('\'', "\\'")
+        ),
+        None => String::new(),
+    };
+    format!(
+        "my ($root, $standalone); if (open(my $fh, '<', $ARGV[-1])) {{ my $top = 1; while (<$fh>) {{ $top = 0 unless /^\\s*(%|$)/; if ($top and /^\\s*%\\s*!\\s*tex\\s+root\\s*=\\s*(.*?)\\s*$/i) {{ $root = $1; last; }} if (/^[^%]*\\\\documentclass/) {{ $standalone = 1; last; }} }} close($fh); }} if (defined $root) {{ $ARGV[-1] = $root =~ m{{^([/\\\\]|[A-Za-z]:)}} ? $root : ($ARGV[-1] =~ s{{[^/\\\\]*$}}{{}}r) . $root; }}{fallback}"
+    )
+}
+
+/// TeX program names accepted in `% !TEX program` magic comments (besides case variants).
+const PROGRAMS: &[&str] = &[
+    "pdflatex",
+    "xelatex",
+    "lualatex",
+    "latex",
+    "pdflatexmk",
+    "xelatexmk",
+    "lualatexmk",
+    "latexmk",
+];
+
+/// Perl code for `latexmk -e` choosing the engine of the document to build (the last
+/// argument, once its root has been followed): the one requested by its `% !TEX program`
+/// magic comment, or else `unicode_engine` if its preamble loads packages needing one, or else
+/// pdfLaTeX. The engine chosen beforehand is kept if the document cannot be read.
+pub fn select_engine(unicode_engine: Engine) -> String {
+    let modes = PROGRAMS
+        .iter()
+        .filter_map(|program| {
+            Some(format!(
+                "'{program}' => {}",
+                Engine::from_program(program)?.pdf_mode()
+            ))
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        "if (open(my $fh, '<', $ARGV[-1])) {{ my %modes = ({modes}); my ($mode, $top, $preamble) = (undef, 1, ''); while (<$fh>) {{ $top = 0 unless /^\\s*(%|$)/; if ($top and !defined $mode and /^\\s*%+\\s*!\\s*tex\\s*(?:ts-)?program\\s*=\\s*(.*?)\\s*$/i and exists $modes{{lc $1}}) {{ $mode = $modes{{lc $1}}; }} s/(?<!\\\\)%.*//s; last if /\\\\begin\\s*\\{{document\\}}/; $preamble .= $_; }} close($fh); $mode //= $preamble =~ /\\\\(?:usepackage|RequirePackage)\\s*(?:\\[[^\\]]*\\])?\\s*\\{{(?:[^}}]*,)?\\s*(?:{packages})\\s*(?:,[^}}]*)?\\}}/ ? {unicode} : {pdflatex}; $pdf_mode = $mode; $dvi_mode = $mode ? 0 : 1; $postscript_mode = 0; }}",
+        packages = UNICODE_ENGINE_PACKAGES.join("|"),
+        unicode = unicode_engine.pdf_mode(),
+        pdflatex = Engine::Pdflatex.pdf_mode(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_program_magic_comment() {
+        assert_eq!(
+            RootDocument::parse("% !TEX program = lualatex\n\\documentclass{article}").program,
+            Some(Engine::Lualatex)
+        );
+        assert_eq!(
+            RootDocument::parse("%!TeX TS-program = XeLaTeX\n").program,
+            Some(Engine::Xelatex)
+        );
+        assert_eq!(
+            RootDocument::parse("% !TEX encoding = UTF-8\n\n%!TEX program=pdflatexmk\n").program,
+            Some(Engine::Pdflatex)
+        );
+    }
+
+    #[test]
+    fn test_magic_comments_only_apply_at_the_top() {
+        assert_eq!(
+            RootDocument::parse("\\documentclass{article}\n% !TEX program = xelatex\n").program,
+            None
+        );
+        assert_eq!(
+            RootDocument::parse("% !TEX program = context\n"),
+            RootDocument::default()
+        );
+    }
+
+    #[test]
+    fn test_unicode_engine_packages_in_preamble() {
+        let needs_unicode_engine = |contents| RootDocument::parse(contents).needs_unicode_engine;
+        assert!(needs_unicode_engine(
+            "\\documentclass{article}\n\\usepackage[T1]{fontenc}\n\\usepackage [math-style=ISO] { amsmath, unicode-math }\n"
+        ));
+        assert!(needs_unicode_engine(
+            "\\documentclass{article}\n\\RequirePackage{polyglossia}\n\\begin{document}\n"
+        ));
+        assert!(!needs_unicode_engine(
+            "\\documentclass{article}\n% \\usepackage{fontspec}\n\\begin{document}\n\\usepackage{fontspec}\n"
+        ));
+        assert!(!needs_unicode_engine("\\usepackage{fontspec-ish}"));
+    }
+
+    #[test]
+    fn test_document_class() {
+        assert_eq!(
+            RootDocument::parse(
+                "% \\documentclass{book}\n\\documentclass[11pt, draft]{ beamer }\n"
+            )
+            .class
+            .as_deref(),
+            Some("beamer")
+        );
+    }
+
+    #[test]
+    fn test_shell_escape_packages_in_preamble() {
+        assert_eq!(
+            RootDocument::parse(
+                "\\documentclass{article}\n\\usepackage[outputdir=build]{minted}\n\\usepackage{svg,xcolor}\n"
+            )
+            .shell_escape_packages,
+            ["minted", "svg"]
+        );
+    }
+
+    #[test]
+    fn test_arara_directives() {
+        assert!(
+            RootDocument::parse(
+                "% arara: pdflatex: { synctex: yes }\n% arara: biber\n\\documentclass{article}\n"
+            )
+            .arara
+        );
+        assert!(!RootDocument::parse("% arara is a build tool\n\\documentclass{article}\n").arara);
+    }
+
+    #[test]
+    fn test_arara_synctex() {
+        let synctex = |contents: &str| RootDocument::parse(contents).arara_synctex;
+        assert!(synctex(
+            "% arara: pdflatex: { synctex: yes }\n% arara: biber\n"
+        ));
+        assert!(synctex(
+            "% arara: lualatex: { shell: true, synctex: true }\n"
+        ));
+        assert!(!synctex("% arara: pdflatex\n% arara: biber\n"));
+        assert!(!synctex("% arara: pdflatex: { synctex: no }\n"));
+        assert!(!synctex("% pdflatex: { synctex: yes }\n"));
+    }
+
+    /// The document `latexmk` builds after running `snippet` for the file `name` with
+    /// `contents`.
+    fn built_document(snippet: &str, name: &str, contents: &str) -> String {
+        run_snippet(snippet, "$ARGV[-1]", name, contents)
+    }
+
+    /// The `$pdf_mode` and `$dvi_mode` of `latexmk` after running `snippet` for the file `name`
+    /// with `contents`.
+    fn latexmk_modes(snippet: &str, name: &str, contents: &str) -> String {
+        run_snippet(snippet, "\"$pdf_mode $dvi_mode\"", name, contents)
+    }
+
+    /// The value of the Perl expression `result` after running `snippet` for the file `name`
+    /// with `contents`. Needs `perl`, as `latexmk` does.
+    fn run_snippet(snippet: &str, result: &str, name: &str, contents: &str) -> String {
+        let directory = std::env::temp_dir().join(format!("zed-latex-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let file = directory.join(name);
+        std::fs::write(&file, contents).unwrap();
+        let output = std::process::Command::new("perl")
+            .args(["-e", &format!("{snippet} print {result};")])
+            .arg(&file)
+            .output()
+            .expect("`perl` is needed to test the `latexmk` snippets");
+        std::fs::remove_file(&file).unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    #[test]
+    fn test_root_magic_comment_fallback_skips_standalone_documents() {
+        let snippet = follow_root_magic_comment(Some("/project/main.tex"));
+        let chapter = built_document(&snippet, "chapter.tex", "\\chapter{Intro}\n");
+        assert_eq!(chapter, "/project/main.tex");
+        let slides = built_document(
+            &snippet,
+            "slides.tex",
+            "% Slides\n\\documentclass{beamer}\n\\begin{document}\n",
+        );
+        assert!(slides.ends_with("/slides.tex"));
+        let commented = built_document(&snippet, "part.tex", "% \\documentclass{article}\n");
+        assert_eq!(commented, "/project/main.tex");
+        let child = built_document(
+            &snippet,
+            "child.tex",
+            "% !TEX root = ../thesis.tex\n\\documentclass{subfiles}\n",
+        );
+        assert!(child.ends_with("/../thesis.tex"));
+    }
+
+    #[test]
+    fn test_engine_follows_program_magic_comment_of_built_document() {
+        let snippet = select_engine(Engine::Lualatex);
+        let modes = |contents| latexmk_modes(&snippet, "cv.tex", contents);
+        assert_eq!(
+            modes("% !TEX program = xelatex\n\\documentclass{moderncv}\n"),
+            "5 0"
+        );
+        assert_eq!(
+            modes("%!TeX TS-program = pdflatexmk\n\\documentclass{article}\n"),
+            "1 0"
+        );
+        assert_eq!(modes("% !TEX program = latex\n"), "0 1");
+        assert_eq!(
+            modes("% !TEX program = context\n\\documentclass{article}\n"),
+            "1 0"
+        );
+        assert_eq!(
+            modes("\\documentclass{article}\n% !TEX program = xelatex\n"),
+            "1 0"
+        );
+    }
+
+    #[test]
+    fn test_engine_follows_unicode_packages_of_built_document() {
+        let snippet = select_engine(Engine::Xelatex);
+        let modes = |contents| latexmk_modes(&snippet, "letter.tex", contents);
+        assert_eq!(
+            modes("\\documentclass{article}\n\\usepackage[T1]{fontenc}\n\\usepackage [math-style=ISO] { amsmath,\n  unicode-math }\n"),
+            "5 0"
+        );
+        assert_eq!(
+            modes("% !TEX program = lualatex\n\\documentclass{article}\n\\usepackage{fontspec}\n"),
+            "4 0"
+        );
+        assert_eq!(
+            modes("\\documentclass{article}\n% \\usepackage{fontspec}\n\\begin{document}\n\\usepackage{polyglossia}\n"),
+            "1 0"
+        );
+        assert_eq!(
+            modes("\\documentclass{article}\n\\usepackage{fontspec-ish}\n"),
+            "1 0"
+        );
+    }
+
+    #[test]
+    fn test_root_magic_comment_snippet() {
+        let snippet = follow_root_magic_comment(Some("/project/it's/main.tex"));
+        assert!(snippet.ends_with("$ARGV[-1] = '/project/it\\'s/main.tex'; }"));
+        assert!(!follow_root_magic_comment(None).contains("elsif"));
+    }
+}
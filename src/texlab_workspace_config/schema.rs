@@ -0,0 +1,98 @@
+//! Generation of the JSON Schema shipped with the extension at
+//! `schemas/texlab-settings.json`, describing the `lsp.texlab` section of Zed's settings:
+//! the texlab `settings` and the extension's own `initialization_options`.
+//!
+//! The schema is derived from [`super::types::WorkspaceSettings`] (the same one used for
+//! validation) and [`ExtensionOptions`], so it stays in sync with the types.
+//! The tests below fail if the shipped file is out of date; run
+//! `UPDATE_SCHEMA=1 cargo test` to regenerate it.
+
+use super::validation::{inline_schema_for, workspace_settings_schema};
+use crate::extension_options::ExtensionOptions;
+use zed_extension_api::serde_json::{json, Value};
+
+const SCHEMA_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/schemas/texlab-settings.json");
+
+/// JSON Schema (draft-07) for Zed settings files, only describing `lsp.texlab` (any other
+/// settings are allowed, so that it can be applied alongside Zed's own schema).
+pub fn texlab_settings_schema() -> Value {
+    json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": "LaTeX extension settings",
+        "type": "object",
+        "properties": {
+            "lsp": {
+                "type": "object",
+                "properties": {
+                    "texlab": {
+                        "type": "object",
+                        "properties": {
+                            "settings": subschema(workspace_settings_schema()),
+                            "initialization_options": subschema(inline_schema_for::<ExtensionOptions>()),
+                        },
+                    },
+                },
+            },
+        },
+    })
+}
+
+/// `schema` without the keywords only meaningful at the root of a schema.
+fn subschema(mut schema: Value) -> Value {
+    if let Value::Object(ref mut map) = schema {
+        map.remove("$schema");
+        map.remove("title");
+    }
+    schema
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zed_extension_api::serde_json;
+
+    #[test]
+    fn test_shipped_schema_is_up_to_date() {
+        let generated = texlab_settings_schema();
+        if std::env::var_os("UPDATE_SCHEMA").is_some() {
+            let contents = serde_json::to_string_pretty(&generated).unwrap() + "\n";
+            std::fs::write(SCHEMA_PATH, contents).unwrap();
+        }
+        let shipped: Value =
+            serde_json::from_str(&std::fs::read_to_string(SCHEMA_PATH).unwrap()).unwrap();
+        assert!(
+            shipped == generated,
+            "{SCHEMA_PATH} is out of date, run `UPDATE_SCHEMA=1 cargo test` to regenerate it"
+        );
+    }
+
+    #[test]
+    fn test_schema_describes_settings_from_doc_comments() {
+        let schema = &texlab_settings_schema()["properties"]["lsp"]["properties"]["texlab"];
+        let forward_search = &schema["properties"]["settings"]["properties"]["texlab"]
+            ["properties"]["forwardSearch"];
+        assert!(forward_search["description"]
+            .as_str()
+            .unwrap()
+            .starts_with("PDF previewer used for forward search."));
+        assert_eq!(
+            forward_search["properties"]["executable"]["type"],
+            json!(["string", "null"])
+        );
+    }
+
+    #[test]
+    fn test_schema_includes_extension_options() {
+        let options = &texlab_settings_schema()["properties"]["lsp"]["properties"]["texlab"]
+            ["properties"]["initialization_options"];
+        assert_eq!(options["additionalProperties"], json!(false));
+        assert!(options["properties"]["build_backend"]["enum"]
+            .as_array()
+            .unwrap()
+            .contains(&json!("tectonic")));
+        assert_eq!(
+            options["properties"]["autoconfig"]["properties"]["chktex"]["type"],
+            json!("boolean")
+        );
+    }
+}
@@ -0,0 +1,221 @@
+//! Detection of the external tools texlab can make use of (and their project configuration),
+//! so that they can be enabled without per-user setup.
+
+use super::latexmkrc::Latexmkrc;
+use serde::Deserialize;
+use zed_extension_api as zed;
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct DetectedTools {
+    /// Absolute path to the project's `.chktexrc`, if found and `chktex` is on PATH.
+    pub chktexrc: Option<String>,
+    /// Whether `latexindent` is on PATH.
+    pub latexindent: bool,
+    /// Absolute path to the project's `latexindent` local settings file, if found.
+    pub latexindent_local: Option<String>,
+    /// Whether `latexmk` is on PATH.
+    pub latexmk: bool,
+    /// The project's latexmkrc, if any.
+    pub latexmkrc: Option<Latexmkrc>,
+    /// Whether `tectonic` is on PATH.
+    pub tectonic: bool,
+    /// Whether `arara` is on PATH.
+    pub arara: bool,
+    /// The first output of the project's `Tectonic.toml`, if the worktree is a Tectonic
+    /// project.
+    pub tectonic_output: Option<TectonicOutput>,
+    /// The project's `Makefile`, if any and `make` is on PATH.
+    pub makefile: Option<BuildFile>,
+    /// The project's `justfile`, if any and `just` is on PATH.
+    pub justfile: Option<BuildFile>,
+    /// Whether the worktree has docstrip sources (see [`has_sources`]).
+    pub docstrip_sources: bool,
+}
+
+/// A `Makefile` or `justfile` at the root of the worktree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildFile {
+    /// Absolute path to the file.
+    pub path: String,
+    /// Names of the targets (recipes) defined in the file.
+    pub targets: Vec<String>,
+}
+
+/// An output of a Tectonic project, built into `build/<name>/<name>.pdf`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TectonicOutput {
+    /// Absolute path to the directory the output is built into.
+    pub directory: String,
+    /// Name of the output.
+    pub name: String,
+}
+
+/// Names (without extension) of the sources looked for by [`has_sources`], besides the name
+/// of the worktree.
+const SOURCE_NAMES: &[&str] = &["main"];
+
+const MAKEFILES: &[&str] = &["GNUmakefile", "makefile", "Makefile"];
+const JUSTFILES: &[&str] = &["justfile", "Justfile", ".justfile"];
+
+/// The part of a `Tectonic.toml` needed to locate the built documents.
+#[derive(Deserialize)]
+struct TectonicProject {
+    #[serde(default)]
+    output: Vec<TectonicProjectOutput>,
+}
+
+#[derive(Deserialize)]
+struct TectonicProjectOutput {
+    name: String,
+}
+
+/// Conventional names of `latexindent` local settings files, in order of preference.
+const LATEXINDENT_LOCAL_SETTINGS_FILES: &[&str] = &[
+    "localSettings.yaml",
+    "latexindent.yaml",
+    ".localSettings.yaml",
+    ".latexindent.yaml",
+];
+
+impl DetectedTools {
+    pub fn for_worktree(worktree: &zed::Worktree) -> Self {
+        let root_path = worktree.root_path();
+        let chktexrc = (worktree.which("chktex").is_some()
+            && worktree.read_text_file(".chktexrc").is_ok())
+        .then(|| format!("{root_path}/.chktexrc"));
+        let latexindent_local = LATEXINDENT_LOCAL_SETTINGS_FILES
+            .iter()
+            .find(|file| worktree.read_text_file(file).is_ok())
+            .map(|file| format!("{root_path}/{file}"));
+        DetectedTools {
+            chktexrc,
+            latexindent: worktree.which("latexindent").is_some(),
+            latexindent_local,
+            latexmk: worktree.which("latexmk").is_some(),
+            latexmkrc: Latexmkrc::for_worktree(worktree),
+            tectonic: worktree.which("tectonic").is_some(),
+            arara: worktree.which("arara").is_some(),
+            tectonic_output: worktree
+                .read_text_file("Tectonic.toml")
+                .ok()
+                .map(|contents| {
+                    let name = tectonic_output(&contents);
+                    TectonicOutput {
+                        directory: format!("{root_path}/build/{name}"),
+                        name,
+                    }
+                }),
+            makefile: worktree
+                .which("make")
+                .and_then(|_| build_file(worktree, MAKEFILES, make_targets)),
+            justfile: worktree
+                .which("just")
+                .and_then(|_| build_file(worktree, JUSTFILES, just_recipes)),
+            docstrip_sources: has_sources(worktree, &["dtx", "ins"]),
+        }
+    }
+}
+
+/// Whether the worktree root has a file with one of `extensions`, named after the worktree (as
+/// package sources conventionally are) or `main`.
+fn has_sources(worktree: &zed::Worktree, extensions: &[&str]) -> bool {
+    let root_path = worktree.root_path();
+    let worktree_name = root_path.rsplit(['/', '\\']).next().unwrap_or_default();
+    std::iter::once(worktree_name)
+        .chain(SOURCE_NAMES.iter().copied())
+        .flat_map(|name| {
+            extensions
+                .iter()
+                .map(move |extension| format!("{name}.{extension}"))
+        })
+        .any(|file| worktree.read_text_file(&file).is_ok())
+}
+
+/// The first of `names` found at the root of the worktree, with the targets found by `parse`.
+fn build_file(
+    worktree: &zed::Worktree,
+    names: &[&str],
+    parse: fn(&str) -> Vec<String>,
+) -> Option<BuildFile> {
+    names.iter().find_map(|name| {
+        let contents = worktree.read_text_file(name).ok()?;
+        Some(BuildFile {
+            path: format!("{}/{name}", worktree.root_path()),
+            targets: parse(&contents),
+        })
+    })
+}
+
+/// Explicit targets of the rules of a `Makefile` (skipping pattern rules and special targets).
+fn make_targets(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .filter(|line| !line.starts_with(['\t', ' ', '#']))
+        .filter_map(|line| {
+            let (targets, rest) = line.split_once(':')?;
+            (!rest.starts_with('=')).then_some(targets)
+        })
+        .filter(|targets| !targets.contains('='))
+        .flat_map(str::split_whitespace)
+        .filter(|target| !target.starts_with('.') && !target.contains(['%', '$']))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Names of the recipes of a `justfile`.
+fn just_recipes(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .filter(|line| !line.starts_with(['\t', ' ', '#', '[']))
+        .filter_map(|line| {
+            let (header, rest) = line.split_once(':')?;
+            if rest.starts_with('=') {
+                return None;
+            }
+            let name = header.split_whitespace().next()?.trim_start_matches('@');
+            (!matches!(name, "set" | "alias" | "export" | "import" | "mod")).then_some(name)
+        })
+        .map(str::to_string)
+        .collect()
+}
+
+/// Name of the first output of a `Tectonic.toml` (`default` as in `tectonic -X new` if it
+/// cannot be read).
+fn tectonic_output(contents: &str) -> String {
+    toml::from_str::<TectonicProject>(contents)
+        .ok()
+        .and_then(|project| project.output.into_iter().next())
+        .map_or_else(|| "default".to_string(), |output| output.name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tectonic_output() {
+        let contents = r#"
+[doc]
+name = "thesis"
+bundle = "https://relay.fullyjustified.net/default_bundle_v33.tar"
+
+[[output]]
+name = "thesis"
+type = "pdf"
+"#;
+        assert_eq!(tectonic_output(contents), "thesis");
+        assert_eq!(tectonic_output("[doc]\nname = 1"), "default");
+    }
+
+    #[test]
+    fn test_make_targets() {
+        let contents = "TEX := lualatex\n.PHONY: pdf clean\n\npdf: figures thesis.pdf\n\tlatexmk thesis.tex\n\n%.png: %.py\n\tpython $<\n\nclean:\n\trm -rf build\n";
+        assert_eq!(make_targets(contents), ["pdf", "clean"]);
+    }
+
+    #[test]
+    fn test_just_recipes() {
+        let contents = "set shell := [\"bash\", \"-c\"]\nalias b := pdf\n\n# Build the PDF\n@pdf profile=\"final\": figures\n    latexmk main.tex\n\nfigures:\n    python plots.py\n";
+        assert_eq!(just_recipes(contents), ["pdf", "figures"]);
+    }
+}
@@ -4,56 +4,82 @@
 //!
 //! The `ForwardSearchSettings` is especially relevant outside this module,
 //! as it can be modified based on the detected PDF previewer.
+//!
+//! The JSON Schema of these types is used to validate the user-provided settings (see
+//! [`super::validation`]), so doc comments on fields are shown to users as descriptions.
+//! Unknown keys are disallowed in the schema so that editors flag them, while validation only
+//! reports (and ignores) them.
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 use std::vec::Vec;
-use zed_extension_api::serde_json::Value;
 
 #[skip_serializing_none]
-#[derive(Debug, Serialize, Deserialize, Default)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Default)]
 #[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
 pub struct WorkspaceSettings {
     pub texlab: Option<TexlabSettings>,
 }
 
 #[skip_serializing_none]
-#[derive(Debug, Serialize, Deserialize, Default)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Default)]
 #[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
 pub struct TexlabSettings {
+    /// Build command run by texlab. Defaults to latexmk if no executable is provided.
     pub build: Option<TexlabBuildSettings>,
-    pub forward_search: Option<TexlabForwardSearchSettings>,
-    pub chktex: Option<Value>,
+    /// PDF previewer used for forward search. Fields not provided are filled in for a detected previewer; set to null to disable this.
+    // `None` if not provided, `Some(None)` if explicitly set to `null`. The latter only
+    // disables autoconfiguration, and is left out when serializing since texlab expects an
+    // object.
+    #[serde(
+        default,
+        deserialize_with = "::serde_with::rust::double_option::deserialize",
+        skip_serializing_if = "is_unset_or_disabled"
+    )]
+    #[schemars(with = "Option<TexlabForwardSearchSettings>")]
+    pub forward_search: Option<Option<TexlabForwardSearchSettings>>,
+    pub chktex: Option<TexlabChktexSettings>,
     pub hover: Option<TexlabHoverSettings>,
-    pub diagnostics: Option<Value>,
-    pub diagnostics_delay: Option<Value>,
-    pub symbols: Option<Value>,
-    pub formatter_line_length: Option<Value>,
-    pub bibtex_formatter: Option<Value>,
-    pub latex_formatter: Option<Value>,
-    pub latexindent: Option<Value>,
-    pub completion: Option<Value>,
-    pub inlay_hints: Option<Value>,
-    pub experimental: Option<Value>,
+    pub diagnostics: Option<TexlabDiagnosticsSettings>,
+    pub diagnostics_delay: Option<u64>,
+    pub symbols: Option<TexlabSymbolsSettings>,
+    pub formatter_line_length: Option<u32>,
+    pub bibtex_formatter: Option<TexlabFormatter>,
+    pub latex_formatter: Option<TexlabFormatter>,
+    pub latexindent: Option<TexlabLatexindentSettings>,
+    pub completion: Option<TexlabCompletionSettings>,
+    pub inlay_hints: Option<TexlabInlayHintsSettings>,
+    pub experimental: Option<TexlabExperimentalSettings>,
+}
+
+fn is_unset_or_disabled(forward_search: &Option<Option<TexlabForwardSearchSettings>>) -> bool {
+    !matches!(forward_search, Some(Some(_)))
 }
 
 #[skip_serializing_none]
-#[derive(Debug, Serialize, Deserialize, Default)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Default)]
 #[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
 pub struct TexlabBuildSettings {
     pub executable: Option<String>,
+    /// Arguments passed to the build executable. %f is replaced by the path of the main document.
     pub args: Option<Vec<String>>,
     pub forward_search_after: Option<bool>,
     pub on_save: Option<bool>,
-    pub use_file_list: Option<Value>,
-    pub aux_directory: Option<Value>,
-    pub log_directory: Option<Value>,
-    pub pdf_directory: Option<Value>,
+    pub use_file_list: Option<bool>,
+    pub aux_directory: Option<String>,
+    pub log_directory: Option<String>,
+    pub pdf_directory: Option<String>,
+    pub filename: Option<String>,
 }
 
 #[skip_serializing_none]
-#[derive(Debug, Serialize, Deserialize, Default)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Default)]
 #[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
 pub struct TexlabHoverSettings {
     pub symbols: String,
 }
@@ -74,13 +100,137 @@ impl TexlabBuildSettings {
 }
 
 #[skip_serializing_none]
-#[derive(Debug, Serialize, Deserialize, Default)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Default)]
 #[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
 pub struct TexlabForwardSearchSettings {
     pub executable: Option<String>,
+    /// Arguments passed to the previewer. Placeholders: %f (TeX file), %p (PDF file), %l (line number).
     pub args: Option<Vec<String>>,
 }
 
+impl TexlabForwardSearchSettings {
+    /// Fill in any fields not provided (by the user) from `preset`, so that e.g. only
+    /// the executable can be swapped for a wrapper script while keeping the preset's args.
+    pub fn or(self, preset: TexlabForwardSearchSettings) -> Self {
+        TexlabForwardSearchSettings {
+            executable: self.executable.or(preset.executable),
+            args: self.args.or(preset.args),
+        }
+    }
+}
+
+/// `texlab.chktex`: when (and how) ChkTeX is run to lint LaTeX documents.
+#[skip_serializing_none]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct TexlabChktexSettings {
+    pub on_open_and_save: Option<bool>,
+    pub on_edit: Option<bool>,
+    pub additional_args: Option<Vec<String>>,
+}
+
+/// `texlab.diagnostics`: regular expressions filtering the reported diagnostics by message.
+#[skip_serializing_none]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct TexlabDiagnosticsSettings {
+    pub allowed_patterns: Option<Vec<String>>,
+    pub ignored_patterns: Option<Vec<String>>,
+}
+
+/// `texlab.symbols`: filtering of document symbols and user-defined environments.
+#[skip_serializing_none]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct TexlabSymbolsSettings {
+    pub allowed_patterns: Option<Vec<String>>,
+    pub ignored_patterns: Option<Vec<String>>,
+    pub custom_environments: Option<Vec<TexlabSymbolEnvironment>>,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct TexlabSymbolEnvironment {
+    pub name: String,
+    pub display_name: Option<String>,
+    pub label: Option<bool>,
+}
+
+/// Values accepted by `texlab.latexFormatter` and `texlab.bibtexFormatter`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TexlabFormatter {
+    Texlab,
+    Latexindent,
+    None,
+}
+
+/// `texlab.latexindent`: options passed on to `latexindent` when it is the formatter.
+#[skip_serializing_none]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct TexlabLatexindentSettings {
+    pub local: Option<String>,
+    pub modify_line_breaks: Option<bool>,
+    pub replacement: Option<String>,
+}
+
+/// `texlab.completion`
+#[skip_serializing_none]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct TexlabCompletionSettings {
+    pub matcher: Option<TexlabCompletionMatcher>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum TexlabCompletionMatcher {
+    Fuzzy,
+    FuzzyIgnoreCase,
+    Prefix,
+    PrefixIgnoreCase,
+}
+
+/// `texlab.inlayHints`
+#[skip_serializing_none]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct TexlabInlayHintsSettings {
+    pub label_definitions: Option<bool>,
+    pub label_references: Option<bool>,
+    pub max_length: Option<u32>,
+}
+
+/// `texlab.experimental`: settings texlab may change or remove between releases.
+///
+/// The `*Prefixes` fields are lists of `[command, prefix]` pairs.
+#[skip_serializing_none]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct TexlabExperimentalSettings {
+    pub follow_package_links: Option<bool>,
+    pub math_environments: Option<Vec<String>>,
+    pub enum_environments: Option<Vec<String>>,
+    pub verbatim_environments: Option<Vec<String>>,
+    pub citation_commands: Option<Vec<String>>,
+    pub label_definition_commands: Option<Vec<String>>,
+    pub label_reference_commands: Option<Vec<String>>,
+    pub label_reference_range_commands: Option<Vec<String>>,
+    pub label_definition_prefixes: Option<Vec<(String, String)>>,
+    pub label_reference_prefixes: Option<Vec<(String, String)>>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -121,7 +271,7 @@ mod tests {
         assert_eq!(build_settings.forward_search_after, Some(true));
 
         assert!(texlab_settings.forward_search.is_some());
-        let forward_search_settings = texlab_settings.forward_search.unwrap();
+        let forward_search_settings = texlab_settings.forward_search.unwrap().unwrap();
         assert_eq!(
             forward_search_settings.executable,
             Some("zathura".to_string())
@@ -175,4 +325,137 @@ mod tests {
         let serialized = serde_json::to_value(&forward_search_settings).unwrap();
         assert_eq!(serialized, expected_json);
     }
+
+    #[test]
+    fn test_round_trip_full_texlab_settings() {
+        let data = json!({
+            "texlab": {
+                "build": {
+                    "executable": "latexmk",
+                    "args": ["-pdf", "%f"],
+                    "forwardSearchAfter": false,
+                    "onSave": true,
+                    "useFileList": true,
+                    "auxDirectory": "build",
+                    "logDirectory": "build",
+                    "pdfDirectory": "out",
+                    "filename": "main.pdf"
+                },
+                "forwardSearch": {
+                    "executable": "zathura",
+                    "args": ["%p"]
+                },
+                "chktex": {
+                    "onOpenAndSave": true,
+                    "onEdit": false,
+                    "additionalArgs": ["-n1"]
+                },
+                "hover": {
+                    "symbols": "glyph"
+                },
+                "diagnostics": {
+                    "allowedPatterns": ["Overfull"],
+                    "ignoredPatterns": ["Underfull"]
+                },
+                "diagnosticsDelay": 300,
+                "symbols": {
+                    "allowedPatterns": [],
+                    "ignoredPatterns": ["^figure"],
+                    "customEnvironments": [
+                        { "name": "theorem", "displayName": "Theorem", "label": true },
+                        { "name": "lemma" }
+                    ]
+                },
+                "formatterLineLength": 80,
+                "bibtexFormatter": "texlab",
+                "latexFormatter": "latexindent",
+                "latexindent": {
+                    "local": "indent.yaml",
+                    "modifyLineBreaks": true,
+                    "replacement": "-rv"
+                },
+                "completion": {
+                    "matcher": "fuzzy-ignore-case"
+                },
+                "inlayHints": {
+                    "labelDefinitions": true,
+                    "labelReferences": false,
+                    "maxLength": 20
+                },
+                "experimental": {
+                    "followPackageLinks": true,
+                    "mathEnvironments": ["mymath"],
+                    "enumEnvironments": ["myenum"],
+                    "verbatimEnvironments": ["myverbatim"],
+                    "citationCommands": ["mycite"],
+                    "labelDefinitionCommands": ["mylabel"],
+                    "labelReferenceCommands": ["myref"],
+                    "labelReferenceRangeCommands": ["myrange"],
+                    "labelDefinitionPrefixes": [["thmlabel", "thm:"]],
+                    "labelReferencePrefixes": [["thmref", "thm:"]]
+                }
+            }
+        });
+
+        let settings: WorkspaceSettings = serde_json::from_value(data.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&settings).unwrap(), data);
+    }
+
+    #[test]
+    fn test_round_trip_omits_unset_fields() {
+        let data = json!({
+            "texlab": {
+                "chktex": { "onEdit": true },
+                "latexFormatter": "none",
+                "experimental": {}
+            }
+        });
+
+        let settings: WorkspaceSettings = serde_json::from_value(data.clone()).unwrap();
+        let texlab_settings = settings.texlab.as_ref().unwrap();
+        assert_eq!(texlab_settings.latex_formatter, Some(TexlabFormatter::None));
+        assert!(texlab_settings
+            .chktex
+            .as_ref()
+            .is_some_and(|chktex| chktex.on_open_and_save.is_none()));
+        assert_eq!(serde_json::to_value(&settings).unwrap(), data);
+    }
+
+    #[test]
+    fn test_deserialize_rejects_wrongly_typed_values() {
+        for data in [
+            json!({ "texlab": { "completion": { "matcher": "exact" } } }),
+            json!({ "texlab": { "diagnosticsDelay": "300" } }),
+            json!({ "texlab": { "build": { "useFileList": "yes" } } }),
+        ] {
+            assert!(serde_json::from_value::<WorkspaceSettings>(data).is_err());
+        }
+    }
+
+    #[test]
+    fn test_disabled_forward_search_is_not_serialized() {
+        let settings = WorkspaceSettings {
+            texlab: Some(TexlabSettings {
+                forward_search: Some(None),
+                ..Default::default()
+            }),
+        };
+        assert_eq!(
+            serde_json::to_value(&settings).unwrap(),
+            json!({ "texlab": {} })
+        );
+    }
+
+    #[test]
+    fn test_deserialize_explicit_null_forward_search() {
+        let settings: WorkspaceSettings =
+            serde_json::from_value(json!({ "texlab": { "forwardSearch": null } })).unwrap();
+        assert!(matches!(
+            settings.texlab.unwrap().forward_search,
+            Some(None)
+        ));
+
+        let settings: WorkspaceSettings = serde_json::from_value(json!({ "texlab": {} })).unwrap();
+        assert!(settings.texlab.unwrap().forward_search.is_none());
+    }
 }
@@ -0,0 +1,384 @@
+//! Validation of the user-provided `lsp.texlab.settings` against the JSON Schema derived
+//! from the settings types understood by this extension (see [`super::types`]).
+//!
+//! Deserialization alone silently ignores unknown keys, so a typo such as
+//! `forwardSerach` or `onsave` would simply have no effect. Instead, the raw JSON
+//! is walked and every unknown key (with the closest valid spelling, if any) and
+//! every wrongly typed value is reported along with its JSON path.
+//!
+//! Unknown keys are reported and dropped (the settings are passed on to texlab as modelled
+//! by [`WorkspaceSettings`]), so that a typo doesn't discard the whole configuration;
+//! wrongly typed values make the settings unusable.
+
+use super::types::WorkspaceSettings;
+use schemars::{generate::SchemaSettings, JsonSchema};
+use zed_extension_api::serde_json::{Map, Value};
+
+/// Problems found in the user-provided settings by [`validate`].
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Problems {
+    /// Unknown keys, with the closest valid spelling (if any).
+    pub unknown: Vec<String>,
+    /// Wrongly typed values.
+    pub invalid: Vec<String>,
+}
+
+/// JSON Schema (draft-07) of `lsp.texlab.settings`, derived from [`WorkspaceSettings`].
+pub fn workspace_settings_schema() -> Value {
+    inline_schema_for::<WorkspaceSettings>()
+}
+
+/// JSON Schema (draft-07) of `T`, with every subschema inlined so that it can be walked
+/// without resolving references.
+pub fn inline_schema_for<T: JsonSchema>() -> Value {
+    let mut settings = SchemaSettings::draft07();
+    settings.inline_subschemas = true;
+    settings
+        .into_generator()
+        .into_root_schema_for::<T>()
+        .to_value()
+}
+
+/// Walks `value` and describes every problem found with respect to `schema` (as generated
+/// by [`workspace_settings_schema`]).
+///
+/// `null` is accepted anywhere, since it is equivalent to leaving a setting unset.
+pub fn validate(value: &Value, schema: &Value, path: &str) -> Problems {
+    let mut problems = Problems::default();
+    check(value, schema, path, &mut problems);
+    problems
+}
+
+/// Formats the wrongly typed values found by [`validate`] into a single error message.
+pub fn report(problems: &[String]) -> String {
+    format!(
+        "invalid lsp.texlab.settings:\n{}",
+        problems
+            .iter()
+            .map(|problem| format!("  - {problem}"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    )
+}
+
+fn check(value: &Value, schema: &Value, path: &str, problems: &mut Problems) {
+    if value.is_null() {
+        return;
+    }
+    // `Option`s of types which are not a single JSON type are generated as `anyOf` the type
+    // and `null`.
+    if let Some(alternatives) = schema.get("anyOf").and_then(Value::as_array) {
+        if let Some(schema) = alternatives
+            .iter()
+            .find(|alternative| alternative.get("type") != Some(&Value::from("null")))
+        {
+            check(value, schema, path, problems);
+        }
+        return;
+    }
+    let expected = expected_type(schema);
+    let matches = match (expected, value) {
+        (None, _)
+        | (Some("boolean"), Value::Bool(_))
+        | (Some("string"), Value::String(_))
+        | (Some("array"), Value::Array(_))
+        | (Some("object"), Value::Object(_)) => true,
+        (Some("integer"), Value::Number(number)) => number.is_u64(),
+        _ => false,
+    };
+    if !matches {
+        problems.invalid.push(format!(
+            "`{path}`: expected {}, found {}",
+            describe_schema(schema),
+            describe_value(value)
+        ));
+        return;
+    }
+    match value {
+        Value::String(string) => check_enum(string, schema, path, problems),
+        Value::Array(items) => check_items(items, schema, path, problems),
+        Value::Object(map) => check_properties(map, schema, path, problems),
+        _ => {}
+    }
+}
+
+/// The JSON type of the values described by `schema` (other than `null`), if restricted.
+fn expected_type(schema: &Value) -> Option<&str> {
+    match schema.get("type")? {
+        Value::String(expected) => Some(expected),
+        Value::Array(types) => types
+            .iter()
+            .filter_map(Value::as_str)
+            .find(|expected| *expected != "null"),
+        _ => None,
+    }
+}
+
+fn check_enum(string: &str, schema: &Value, path: &str, problems: &mut Problems) {
+    let Some(allowed) = schema.get("enum").and_then(Value::as_array) else {
+        return;
+    };
+    let allowed: Vec<&str> = allowed.iter().filter_map(Value::as_str).collect();
+    if !allowed.contains(&string) {
+        problems.invalid.push(format!(
+            "`{path}`: unknown value \"{string}\"{}, expected one of {}",
+            did_you_mean(string, allowed.iter().copied()),
+            allowed
+                .iter()
+                .map(|allowed| format!("\"{allowed}\""))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+}
+
+fn check_items(items: &[Value], schema: &Value, path: &str, problems: &mut Problems) {
+    match schema.get("items") {
+        // Fixed-length arrays, e.g. `[command, prefix]` pairs.
+        Some(Value::Array(item_schemas)) => {
+            if item_schemas.len() != items.len() {
+                problems.invalid.push(format!(
+                    "`{path}`: expected {}, found an array",
+                    describe_schema(schema)
+                ));
+                return;
+            }
+            for (index, (item, item_schema)) in items.iter().zip(item_schemas).enumerate() {
+                check(item, item_schema, &format!("{path}[{index}]"), problems);
+            }
+        }
+        Some(item_schema) => {
+            for (index, item) in items.iter().enumerate() {
+                check(item, item_schema, &format!("{path}[{index}]"), problems);
+            }
+        }
+        None => {}
+    }
+}
+
+fn check_properties(map: &Map<String, Value>, schema: &Value, path: &str, problems: &mut Problems) {
+    let properties = schema.get("properties").and_then(Value::as_object);
+    for (key, field_value) in map {
+        let field_path = if path.is_empty() {
+            key.clone()
+        } else {
+            format!("{path}.{key}")
+        };
+        match (
+            properties.and_then(|properties| properties.get(key)),
+            schema.get("additionalProperties"),
+        ) {
+            (Some(field_schema), _) => check(field_value, field_schema, &field_path, problems),
+            // Maps, e.g. build profiles by name.
+            (None, Some(field_schema)) if field_schema.is_object() => {
+                check(field_value, field_schema, &field_path, problems)
+            }
+            (None, _) => problems.unknown.push(format!(
+                "`{field_path}`: unknown setting{}",
+                did_you_mean(
+                    key,
+                    properties
+                        .into_iter()
+                        .flat_map(|properties| properties.keys().map(String::as_str))
+                )
+            )),
+        }
+    }
+}
+
+fn did_you_mean<'a>(input: &str, candidates: impl Iterator<Item = &'a str>) -> String {
+    let input_lowercase = input.to_lowercase();
+    candidates
+        .map(|candidate| {
+            (
+                edit_distance(&input_lowercase, &candidate.to_lowercase()),
+                candidate,
+            )
+        })
+        .filter(|(distance, _)| *distance <= (input.chars().count() / 3).max(2))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| format!(" (did you mean `{candidate}`?)"))
+        .unwrap_or_default()
+}
+
+/// Levenshtein distance between two strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous_row: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current_row = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous_row[j] + usize::from(a_char != *b_char);
+            current_row.push(
+                substitution
+                    .min(previous_row[j + 1] + 1)
+                    .min(current_row[j] + 1),
+            );
+        }
+        previous_row = current_row;
+    }
+    previous_row[b.len()]
+}
+
+fn describe_schema(schema: &Value) -> String {
+    if let Some(Value::Array(items)) = schema.get("items") {
+        return format!("an array of {} elements", items.len());
+    }
+    match expected_type(schema) {
+        Some("boolean") => "a boolean".to_string(),
+        Some("integer") => "a non-negative integer".to_string(),
+        Some("string") => "a string".to_string(),
+        Some("array") => "an array".to_string(),
+        Some("object") => "an object".to_string(),
+        _ => "a value".to_string(),
+    }
+}
+
+fn describe_value(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::types::*;
+    use super::*;
+    use zed_extension_api::serde_json::{json, to_value};
+
+    #[test]
+    fn test_schema_accepts_every_settings_field() {
+        // Constructed without `..Default::default()`, so that every new field must be
+        // populated here.
+        let strings = || Some(vec!["value".to_string()]);
+        let settings = WorkspaceSettings {
+            texlab: Some(TexlabSettings {
+                build: Some(TexlabBuildSettings {
+                    executable: Some("latexmk".to_string()),
+                    args: strings(),
+                    forward_search_after: Some(true),
+                    on_save: Some(true),
+                    use_file_list: Some(true),
+                    aux_directory: Some("build".to_string()),
+                    log_directory: Some("build".to_string()),
+                    pdf_directory: Some("build".to_string()),
+                    filename: Some("main.pdf".to_string()),
+                }),
+                forward_search: Some(Some(TexlabForwardSearchSettings {
+                    executable: Some("zathura".to_string()),
+                    args: strings(),
+                })),
+                chktex: Some(TexlabChktexSettings {
+                    on_open_and_save: Some(true),
+                    on_edit: Some(true),
+                    additional_args: strings(),
+                }),
+                hover: Some(TexlabHoverSettings {
+                    symbols: "glyph".to_string(),
+                }),
+                diagnostics: Some(TexlabDiagnosticsSettings {
+                    allowed_patterns: strings(),
+                    ignored_patterns: strings(),
+                }),
+                diagnostics_delay: Some(300),
+                symbols: Some(TexlabSymbolsSettings {
+                    allowed_patterns: strings(),
+                    ignored_patterns: strings(),
+                    custom_environments: Some(vec![TexlabSymbolEnvironment {
+                        name: "theorem".to_string(),
+                        display_name: Some("Theorem".to_string()),
+                        label: Some(true),
+                    }]),
+                }),
+                formatter_line_length: Some(80),
+                bibtex_formatter: Some(TexlabFormatter::Texlab),
+                latex_formatter: Some(TexlabFormatter::Latexindent),
+                latexindent: Some(TexlabLatexindentSettings {
+                    local: Some("indent.yaml".to_string()),
+                    modify_line_breaks: Some(true),
+                    replacement: Some("-rv".to_string()),
+                }),
+                completion: Some(TexlabCompletionSettings {
+                    matcher: Some(TexlabCompletionMatcher::FuzzyIgnoreCase),
+                }),
+                inlay_hints: Some(TexlabInlayHintsSettings {
+                    label_definitions: Some(true),
+                    label_references: Some(true),
+                    max_length: Some(20),
+                }),
+                experimental: Some(TexlabExperimentalSettings {
+                    follow_package_links: Some(true),
+                    math_environments: strings(),
+                    enum_environments: strings(),
+                    verbatim_environments: strings(),
+                    citation_commands: strings(),
+                    label_definition_commands: strings(),
+                    label_reference_commands: strings(),
+                    label_reference_range_commands: strings(),
+                    label_definition_prefixes: Some(vec![("thm".to_string(), "thm:".to_string())]),
+                    label_reference_prefixes: Some(vec![("thm".to_string(), "thm:".to_string())]),
+                }),
+            }),
+        };
+        let data = to_value(&settings).unwrap();
+        assert_eq!(
+            validate(&data, &workspace_settings_schema(), ""),
+            Problems::default()
+        );
+    }
+
+    #[test]
+    fn test_unknown_keys_suggest_closest_spelling() {
+        let data = json!({
+            "texlab": {
+                "forwardSerach": { "executable": "zathura" },
+                "build": { "onsave": true, "somethingElse": 1 }
+            }
+        });
+        let problems = validate(&data, &workspace_settings_schema(), "");
+        assert_eq!(
+            problems.unknown,
+            vec![
+                "`texlab.build.onsave`: unknown setting (did you mean `onSave`?)",
+                "`texlab.build.somethingElse`: unknown setting",
+                "`texlab.forwardSerach`: unknown setting (did you mean `forwardSearch`?)",
+            ]
+        );
+        assert!(problems.invalid.is_empty());
+    }
+
+    #[test]
+    fn test_type_mismatches_are_reported_with_path() {
+        let data = json!({
+            "texlab": {
+                "build": { "args": ["-pdf", 3] },
+                "diagnosticsDelay": "300",
+                "completion": { "matcher": "fuzy" },
+                "experimental": { "labelDefinitionPrefixes": [["thm"]] }
+            }
+        });
+        let problems = validate(&data, &workspace_settings_schema(), "");
+        assert_eq!(
+            problems.invalid,
+            vec![
+                "`texlab.build.args[1]`: expected a string, found a number",
+                "`texlab.completion.matcher`: unknown value \"fuzy\" (did you mean `fuzzy`?), expected one of \"fuzzy\", \"fuzzy-ignore-case\", \"prefix\", \"prefix-ignore-case\"",
+                "`texlab.diagnosticsDelay`: expected a non-negative integer, found a string",
+                "`texlab.experimental.labelDefinitionPrefixes[0]`: expected an array of 2 elements, found an array",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_null_and_empty_settings_are_valid() {
+        let schema = workspace_settings_schema();
+        assert_eq!(validate(&Value::Null, &schema, ""), Problems::default());
+        let data = json!({ "texlab": { "build": null, "chktex": {}, "forwardSearch": null } });
+        assert_eq!(validate(&data, &schema, ""), Problems::default());
+    }
+}
@@ -0,0 +1,41 @@
+//! Files generated in the extension's work directory for a worktree (`latexindent` local
+//! settings and `latexmk` rules), named after a hash of the worktree root path so that the
+//! worktrees open at the same time don't overwrite each other's files.
+
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+/// Writes `contents` to the file generated as `stem.extension` for the worktree at
+/// `worktree_root`, returning its absolute path.
+pub fn write(worktree_root: &str, stem: &str, extension: &str, contents: &str) -> Option<String> {
+    let name = file_name(worktree_root, stem, extension);
+    std::fs::write(&name, contents).ok()?;
+    Some(format!(
+        "{}/{name}",
+        std::env::current_dir().ok()?.to_str()?
+    ))
+}
+
+fn file_name(worktree_root: &str, stem: &str, extension: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    worktree_root.hash(&mut hasher);
+    format!("{stem}-{:016x}.{extension}", hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_names_differ_by_worktree() {
+        let name = file_name("/home/user/thesis", "latexindent-zed", "yaml");
+        assert!(name.starts_with("latexindent-zed-") && name.ends_with(".yaml"));
+        assert_eq!(
+            name,
+            file_name("/home/user/thesis", "latexindent-zed", "yaml")
+        );
+        assert_ne!(
+            name,
+            file_name("/home/user/paper", "latexindent-zed", "yaml")
+        );
+    }
+}
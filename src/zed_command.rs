@@ -5,39 +5,47 @@
 //! - Standard installations ("zed")
 //! - Flatpak installations on Linux
 //! - Alternative package manager installations (creating executable "zeditor", "zedit", or "zed-editor")
+//! - A command provided by the user (`zed_command` extension option)
 //!
 //! # Examples
 //! ```
 //! use zed_extension_api::{self as zed, Worktree};
 //! // ...
 //! // worktree: Worktree
-//! if let Some(cmd) = CommandName::determine(&worktree) {
+//! if let Some(cmd) = CommandName::determine(&worktree, None) {
 //!     println!("Zed can be launched with command: {}", cmd.to_str());
 //! }
 //! ```
 use zed_extension_api as zed;
 
-#[derive(Copy, Clone)]
+#[derive(Clone, Default)]
 pub enum CommandName {
+    #[default]
     Zed,
     Zeditor,
     Zedit,
     ZedEditor,
     Flatpak,
+    /// User-provided command
+    Custom(String),
 }
 
 impl CommandName {
-    pub fn to_str(&self) -> &'static str {
+    pub fn to_str(&self) -> &str {
         match self {
             CommandName::Zed => "zed",
             CommandName::Zeditor => "zeditor",
             CommandName::Zedit => "zedit",
             CommandName::ZedEditor => "zed-editor",
             CommandName::Flatpak => "flatpak run dev.zed.Zed",
+            CommandName::Custom(command) => command,
         }
     }
 
-    pub fn determine(worktree: &zed::Worktree) -> Option<Self> {
+    pub fn determine(worktree: &zed::Worktree, user_provided: Option<&str>) -> Option<Self> {
+        if let Some(command) = user_provided {
+            return Some(CommandName::Custom(command.to_string()));
+        }
         if zed::Os::Linux == zed::current_platform().0 {
             // The existence of the ZED_FLATPAK_LIB_PATH environment variable is
             // a very strong indicator that Zed is running through flatpak.
@@ -80,9 +88,3 @@ impl CommandName {
         None
     }
 }
-
-impl Default for CommandName {
-    fn default() -> Self {
-        CommandName::Zed
-    }
-}